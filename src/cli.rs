@@ -1,45 +1,268 @@
-use crate::model::{Priority, TodoList};
-use clap::{Parser, Subcommand};
+use crate::date::{Date, now, parse_due, parse_duration_arg};
+use crate::model::{Priority, Sort, Status, Task, TodoList};
+use crate::persistence::{
+    PATH, append_archive, archive_path, list_snapshots, load_archive, load_snapshot,
+    load_todo_list, profile_path, save_snapshot, save_todo_list, write_atomic,
+};
+use crate::query::parse_query;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::{BufRead, Read, Write};
 use thiserror::Error;
 
 #[derive(Parser)]
 #[command(name = "todo")]
 #[command(about = "a simple cli todo manager")]
 pub struct Cli {
+    /// use a named profile instead of the default list
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// save the list as single-line JSON instead of pretty-printed
+    #[arg(long, global = true)]
+    pub compact: bool,
+    /// show what a command would do without saving anything
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    /// treat the task ids given to commands as list positions
+    #[arg(long, global = true)]
+    pub by_index: bool,
+    /// only print listings and errors, not confirmations
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// starts the interactive mode when left out
     #[command(subcommand)]
-    pub command: Command,
+    pub command: Option<Command>,
 }
 
 #[derive(Subcommand, Debug, PartialEq, Clone)]
 pub enum Command {
     /// adds a new task
     Add {
-        /// contents of the task
+        /// contents of the task, or - to read it from stdin
         text: String,
+        /// place the new task right after the task with this id
+        #[arg(long)]
+        after: Option<u32>,
+        /// priority level [low|med|high or 1-3], low when left out
+        #[arg(long)]
+        priority: Option<Priority>,
     },
     /// lists all tasks
-    List,
+    List {
+        /// only show tasks with this status [all|done|todo]
+        #[arg(long, alias = "status", default_value = "all")]
+        only: Status,
+        /// only show tasks with this priority [low|med|high or 1-3]
+        #[arg(long)]
+        priority: Option<Priority>,
+        /// show at most this many tasks, 0 for no limit
+        #[arg(long, default_value_t = 0)]
+        limit: usize,
+        /// leave out completed tasks, noting how many were hidden
+        #[arg(long, conflicts_with = "only")]
+        hide_done: bool,
+        /// only show tasks added within this long, like 24h, 7d or 2w
+        #[arg(long, value_parser = parse_duration_arg)]
+        since: Option<u64>,
+    },
     /// lists tasks you've completed
-    ListDone,
+    ListDone {
+        /// show at most this many tasks, 0 for no limit
+        #[arg(long, default_value_t = 0)]
+        limit: usize,
+        /// only tasks completed on or after this date
+        #[arg(long, value_parser = parse_due)]
+        after: Option<Date>,
+        /// only tasks completed on or before this date
+        #[arg(long, value_parser = parse_due)]
+        before: Option<Date>,
+    },
     /// lists tasks that need to be completed
-    ListTodo,
+    ListTodo {
+        /// order to list tasks in, defaults to the saved sort [position|urgency]
+        #[arg(long)]
+        sort: Option<Sort>,
+        /// show at most this many tasks, 0 for no limit
+        #[arg(long, default_value_t = 0)]
+        limit: usize,
+    },
     /// lists tasks by given priority
     ListByPriority {
-        /// priority level [low|med|high]
+        /// priority level [low|med|high or 1-3]
         priority: Priority,
+        /// show at most this many tasks, 0 for no limit
+        #[arg(long, default_value_t = 0)]
+        limit: usize,
+    },
+    /// lists tasks matching a query like "priority:high done:false tag:work"
+    Query {
+        /// space separated key:value clauses, keys are priority, done, tag, text
+        expr: String,
+    },
+    /// lists incomplete tasks due today or earlier
+    Today,
+    /// lists incomplete tasks with a due date, soonest first
+    ListDue,
+    /// shows how much of the list is done
+    Progress,
+    /// prints how many tasks there are at each priority
+    PriorityCounts,
+    /// prints the number of tasks, and nothing else
+    Count {
+        /// only count tasks with this status [all|done|todo]
+        filter: Option<Status>,
     },
     /// mark task as done
     Done {
         /// task id
         id: u32,
     },
+    /// mark a task as done, or as not done if it already is
+    Toggle {
+        /// task id
+        id: u32,
+    },
     /// set priority of a task
     SetPriority {
+        /// priority level [low|med|high or 1-3]
+        priority: Priority,
+        /// ids of the tasks to change
+        #[arg(required = true)]
+        ids: Vec<u32>,
+    },
+    /// print a task in full, including text spanning several lines
+    Show {
         /// task id
         id: u32,
-        /// priority level [low|med|high]
-        priority: Priority,
     },
+    /// add a copy of an existing task
+    Duplicate {
+        /// task id
+        id: u32,
+    },
+    /// change the text of a task
+    Edit {
+        /// task id
+        id: u32,
+        /// new contents of the task
+        #[arg(required_unless_present = "append", conflicts_with = "append")]
+        text: Option<String>,
+        /// add this to the end of the existing text instead
+        #[arg(long)]
+        append: Option<String>,
+    },
+    /// add tags to a task
+    Tag {
+        /// task id
+        id: u32,
+        /// tags to add
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// lists every tag in use with how many tasks carry it
+    Tags,
+    /// rename a tag on every task that has it
+    RenameTag {
+        /// current tag
+        from: String,
+        /// new tag
+        to: String,
+    },
+    /// set the due date of a task
+    SetDue {
+        /// task id
+        id: u32,
+        /// due date as YYYY-MM-DD, today, tomorrow or +N[d|w]
+        #[arg(value_parser = parse_due)]
+        date: Date,
+    },
+    /// raise incomplete tasks due by tomorrow to high priority
+    Escalate,
+    /// set the sort list-todo uses when --sort is not given
+    SetSort {
+        /// sort order [position|urgency]
+        sort: Sort,
+    },
+    /// move a task to a new place in the list
+    Move {
+        /// task id
+        id: u32,
+        /// new position in the list (1-based)
+        #[arg(
+            required_unless_present_any = ["before", "after"],
+            conflicts_with_all = ["before", "after"]
+        )]
+        position: Option<usize>,
+        /// place the task before the task with this id
+        #[arg(long, conflicts_with = "after")]
+        before: Option<u32>,
+        /// place the task after the task with this id
+        #[arg(long)]
+        after: Option<u32>,
+    },
+    /// copy a task into another profile, keeping the original
+    Clone {
+        /// task id
+        id: u32,
+        /// profile to copy the task into
+        #[arg(long)]
+        to: String,
+    },
+    /// add every task from another todo file to this list
+    Merge {
+        /// path of the todo file to import
+        path: String,
+    },
+    /// save a named checkpoint of the list
+    Snapshot {
+        /// snapshot name
+        name: String,
+    },
+    /// replace the list with a named checkpoint
+    RestoreSnapshot {
+        /// snapshot name
+        name: String,
+    },
+    /// list available snapshots
+    Snapshots,
+    /// check the list for duplicate ids and other inconsistencies
+    Verify,
+    /// remove tasks with the same text, keeping the oldest
+    Dedup,
+    /// move done tasks out of the list into the archive file
+    Archive,
+    /// lists archived tasks
+    ListArchive,
+    /// print the list as markdown or json
+    Export {
+        /// output format [markdown|json]
+        format: ExportFormat,
+        /// write to this file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// run commands interactively until quit or end of input
+    Repl,
+    /// show the list and redraw it whenever the file changes, until Ctrl-C
+    Watch {
+        /// milliseconds between checks for changes
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+    },
+    /// remove every task and start ids from 1 again
+    Reset {
+        /// skip the confirmation prompt
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+#[value(rename_all = "lower")]
+pub enum ExportFormat {
+    /// a checklist, one `- [ ]` item per task
+    Markdown,
+    /// the list as it is saved
+    Json,
 }
 
 #[derive(Debug, Error)]
@@ -48,60 +271,823 @@ pub enum TodoError {
     UnknownCommand,
     #[error("invalid arguments")]
     MissingArgument,
-    #[error("task with that id was not found")]
-    TaskNotFound,
+    /// `closest` holds the nearest ids that do exist, see `closest_ids`
+    #[error("task {id} not found{}", did_you_mean(closest))]
+    TaskNotFound { id: u32, closest: Vec<u32> },
     #[error("task id must be a positive integer")]
     InvalidId(#[from] std::num::ParseIntError),
-    #[error("failed to save todo list")]
-    SaveError(#[source] std::io::Error),
+    #[error("failed to save todo list: {0}")]
+    SaveError(#[from] std::io::Error),
+    #[error("failed to load {path}: {source}")]
+    LoadError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to parse todo list: {0}")]
+    ParseError(#[from] serde_json::Error),
     #[error("unknown priority")]
     PriorityError,
+    #[error("invalid date, expected YYYY-MM-DD, today, tomorrow or +N[d|w]")]
+    InvalidDate,
+    #[error("no snapshot with that name")]
+    SnapshotNotFound,
+    #[error("snapshot names cannot contain '.' or path separators")]
+    InvalidSnapshotName,
+    #[error("profile names cannot be empty or contain '.' or path separators")]
+    InvalidProfileName,
+    #[error("task text cannot be empty")]
+    EmptyTask,
+    #[error("confirmation required, pass --yes when not running in a terminal")]
+    ConfirmationRequired,
+    #[error("invalid duration, expected a number followed by h, d or w")]
+    InvalidDuration,
+    #[error(
+        "found {} {} in the todo list:\n{}",
+        .0.len(),
+        if .0.len() == 1 { "problem" } else { "problems" },
+        .0.join("\n")
+    )]
+    VerifyFailed(Vec<String>),
+    #[error("todo list has format version {0}, newer than this build supports")]
+    UnsupportedVersion(u32),
+    #[error("invalid query: {0}")]
+    QueryError(String),
+    #[error("failed to read from stdin")]
+    ReadError(#[source] std::io::Error),
+    #[error("the repl reads commands from stdin, give the task text instead of -")]
+    StdinInRepl,
+    #[error("no task at position {0}")]
+    IndexOutOfRange(usize),
+    /// every task a command was given is missing, one message per task
+    #[error("none of the tasks were found:\n{}", .0.join("\n"))]
+    TasksNotFound(Vec<String>),
+    #[error("failed to write export: {0}")]
+    ExportError(#[source] std::io::Error),
+}
+
+/// the `; did you mean 1 or 3?` ending of a `TaskNotFound` message
+fn did_you_mean(closest: &[u32]) -> String {
+    if closest.is_empty() {
+        return String::new();
+    }
+    let ids: Vec<String> = closest.iter().map(|id| id.to_string()).collect();
+    format!("; did you mean {}?", ids.join(" or "))
+}
+
+impl TodoError {
+    /// process exit code for this error, stable so scripts can rely on it
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TodoError::InvalidId(_) => 2,
+            TodoError::TaskNotFound { .. } | TodoError::TasksNotFound(_) => 3,
+            TodoError::SaveError(_) => 4,
+            TodoError::UnknownCommand => 5,
+            TodoError::MissingArgument => 6,
+            TodoError::PriorityError => 7,
+            TodoError::InvalidDate => 8,
+            TodoError::SnapshotNotFound => 9,
+            TodoError::EmptyTask => 10,
+            TodoError::ConfirmationRequired => 11,
+            TodoError::ReadError(_) => 12,
+            TodoError::LoadError { .. } | TodoError::ParseError(_) => 13,
+            TodoError::QueryError(_) => 14,
+            TodoError::ExportError(_) => 15,
+            TodoError::InvalidDuration => 16,
+            TodoError::VerifyFailed(_) => 17,
+            TodoError::UnsupportedVersion(_) => 18,
+            TodoError::InvalidSnapshotName => 19,
+            TodoError::InvalidProfileName => 20,
+            TodoError::StdinInRepl => 21,
+            TodoError::IndexOutOfRange(_) => 22,
+        }
+    }
+}
+
+/// text produced by a command, left to the caller to print
+#[derive(Debug, Default, PartialEq)]
+pub struct Output {
+    pub lines: Vec<String>,
+    quiet: bool,
+}
+
+impl Output {
+    /// output that drops confirmation notes when `quiet` is set
+    pub fn new(quiet: bool) -> Self {
+        Self {
+            lines: Vec::new(),
+            quiet,
+        }
+    }
+
+    pub fn push(&mut self, line: impl Into<String>) {
+        self.lines.push(line.into());
+    }
+
+    /// pushes a confirmation message, which quiet mode leaves out
+    pub fn note(&mut self, line: impl Into<String>) {
+        if !self.quiet {
+            self.push(line);
+        }
+    }
+
+    /// pushes at most `limit` tasks (0 for all) and notes how many were cut
+    fn push_tasks(&mut self, tasks: &[&Task], limit: usize) {
+        let shown = if limit == 0 { tasks.len() } else { limit };
+        let today = Date::today();
+        for task in tasks.iter().take(shown) {
+            self.push(render_task(task, today));
+        }
+        if tasks.len() > shown {
+            self.push(format!("... and {} more", tasks.len() - shown));
+        }
+    }
+}
+
+/// a short readable length of time like `3d 4h`, `2h 5m` or `40s`
+pub fn humanize_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    let (days, hours) = (seconds / 86_400, seconds % 86_400 / 3_600);
+    let (minutes, seconds) = (seconds % 3_600 / 60, seconds % 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+/// the whole list rendered in `format`
+pub fn export(todo_list: &TodoList, format: ExportFormat) -> Result<String, TodoError> {
+    match format {
+        ExportFormat::Markdown => Ok(todo_list
+            .tasks
+            .iter()
+            .map(|task| {
+                let check = if task.done { "x" } else { " " };
+                let text = task.text.split_whitespace().collect::<Vec<_>>().join(" ");
+                format!("- [{check}] {text}\n")
+            })
+            .collect()),
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(todo_list).map_err(|e| TodoError::ExportError(e.into()))
+        }
+    }
+}
+
+/// a bar like `[####------] 40% (2/5)`
+pub fn progress_bar(todo_list: &TodoList) -> String {
+    const WIDTH: usize = 10;
+    let rate = todo_list.completion_rate();
+    let filled = (rate * WIDTH as f64).round() as usize;
+    format!(
+        "[{}{}] {:.0}% ({}/{})",
+        "#".repeat(filled),
+        "-".repeat(WIDTH - filled),
+        rate * 100.0,
+        todo_list.done().len(),
+        todo_list.tasks.len()
+    )
+}
+
+/// a task as shown in listings, flagged when it is past its due date
+pub fn render_task(task: &Task, today: Date) -> String {
+    if task.is_overdue(today) {
+        format!("{task} (!)")
+    } else {
+        task.to_string()
+    }
+}
+
+impl std::fmt::Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// settings from the global flags that change how commands run
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Options {
+    /// leave every file untouched, only reporting what would change
+    pub dry_run: bool,
+    /// commands take 1-based list positions wherever they take task ids
+    pub by_index: bool,
+    /// leave out confirmation messages
+    pub quiet: bool,
+    /// file the list was loaded from, `PATH` when unset; archives and
+    /// snapshots are kept next to it
+    pub path: Option<String>,
+    /// default list that profile files sit next to, `PATH` when unset
+    pub base: Option<String>,
+    /// set by `run_repl`, whose commands come in on stdin
+    pub in_repl: bool,
+    /// the user agreed to a command that `needs_confirmation`
+    pub confirmed: bool,
+}
+
+impl Options {
+    pub fn path(&self) -> &str {
+        self.path.as_deref().unwrap_or(PATH)
+    }
+
+    pub fn base(&self) -> &str {
+        self.base.as_deref().unwrap_or(PATH)
+    }
+}
+
+impl Cli {
+    /// the data file for the selected profile
+    pub fn path(&self) -> Result<String, TodoError> {
+        match &self.profile {
+            Some(profile) => profile_path(PATH, profile),
+            None => Ok(PATH.to_string()),
+        }
+    }
+
+    pub fn options(&self) -> Result<Options, TodoError> {
+        Ok(Options {
+            dry_run: self.dry_run,
+            by_index: self.by_index,
+            quiet: self.quiet,
+            path: Some(self.path()?),
+            base: Some(PATH.to_string()),
+            in_repl: false,
+            confirmed: false,
+        })
+    }
+}
+
+/// swaps list positions for task ids in every command that takes an id;
+/// set-priority keeps going past bad positions, returning their errors
+fn resolve_indices(
+    cmd: Command,
+    todo_list: &TodoList,
+) -> Result<(Command, Vec<TodoError>), TodoError> {
+    let id_at = |index: u32| todo_list.id_at_index(index as usize);
+    let id_at_opt = |index: Option<u32>| index.map(id_at).transpose();
+    let mut skipped = Vec::new();
+    let cmd = match cmd {
+        Command::Add {
+            text,
+            after,
+            priority,
+        } => Command::Add {
+            text,
+            after: id_at_opt(after)?,
+            priority,
+        },
+        Command::Done { id } => Command::Done { id: id_at(id)? },
+        Command::Toggle { id } => Command::Toggle { id: id_at(id)? },
+        Command::SetPriority { priority, ids } => Command::SetPriority {
+            priority,
+            ids: ids
+                .into_iter()
+                .filter_map(|index| id_at(index).map_err(|e| skipped.push(e)).ok())
+                .collect(),
+        },
+        Command::Show { id } => Command::Show { id: id_at(id)? },
+        Command::Duplicate { id } => Command::Duplicate { id: id_at(id)? },
+        Command::Edit { id, text, append } => Command::Edit {
+            id: id_at(id)?,
+            text,
+            append,
+        },
+        Command::Tag { id, tags } => Command::Tag {
+            id: id_at(id)?,
+            tags,
+        },
+        Command::SetDue { id, date } => Command::SetDue {
+            id: id_at(id)?,
+            date,
+        },
+        Command::Move {
+            id,
+            position,
+            before,
+            after,
+        } => Command::Move {
+            id: id_at(id)?,
+            position,
+            before: id_at_opt(before)?,
+            after: id_at_opt(after)?,
+        },
+        Command::Clone { id, to } => Command::Clone { id: id_at(id)?, to },
+        cmd => cmd,
+    };
+    Ok((cmd, skipped))
+}
+
+pub fn execute_command(cmd: Command, todo_list: &mut TodoList) -> Result<Output, TodoError> {
+    execute_command_with(cmd, todo_list, &Options::default())
 }
 
-pub fn execute_command(cmd: Command, todo_list: &mut TodoList) -> Result<(), TodoError> {
+pub fn execute_command_with(
+    cmd: Command,
+    todo_list: &mut TodoList,
+    opts: &Options,
+) -> Result<Output, TodoError> {
+    let (cmd, mut skipped) = if opts.by_index {
+        resolve_indices(cmd, todo_list)?
+    } else {
+        (cmd, Vec::new())
+    };
+    let mut out = Output::new(opts.quiet);
     match cmd {
-        Command::Add { text } => {
-            let task = todo_list.add(text);
-            println!(
+        Command::Add {
+            text,
+            after,
+            priority,
+        } => {
+            let text = if text == "-" {
+                if opts.in_repl {
+                    return Err(TodoError::StdinInRepl);
+                }
+                read_text(std::io::stdin())?
+            } else {
+                text
+            };
+            let priority = priority.unwrap_or_default();
+            let task = match after {
+                Some(anchor) => {
+                    let id = todo_list.add_after(text, anchor)?.id;
+                    todo_list.set_priority(id, priority)?
+                }
+                None => todo_list.add_with_priority(text, priority)?,
+            };
+            out.note(format!(
                 "Added {} as {} with {} priority",
                 task.text, task.id, task.priority
-            );
-            Ok(())
+            ));
         }
-        Command::List => {
-            println!("Tasks:");
-            todo_list.print_list();
-            Ok(())
+        Command::List {
+            only,
+            priority,
+            limit,
+            hide_done,
+            since,
+        } => {
+            out.push("Tasks:");
+            let now = now();
+            let filter = |done| {
+                let mut tasks = todo_list.filter(done, priority);
+                if let Some(seconds) = since {
+                    tasks.retain(|t| t.created_within(seconds, now));
+                }
+                tasks
+            };
+            if hide_done {
+                out.push_tasks(&filter(Some(false)), limit);
+                let hidden = filter(Some(true)).len();
+                if hidden > 0 {
+                    out.push(format!("({hidden} completed hidden)"));
+                }
+            } else {
+                out.push_tasks(&filter(only.done_filter()), limit);
+            }
         }
-        Command::ListDone => {
-            println!("Tasks Done:");
-            todo_list.print_done();
-            Ok(())
+        Command::ListDone {
+            limit,
+            after,
+            before,
+        } => {
+            out.push("Tasks Done:");
+            let tasks = if after.is_none() && before.is_none() {
+                todo_list.done()
+            } else {
+                let start = after.unwrap_or(Date::from_timestamp(0));
+                todo_list.completed_between(start, before.unwrap_or(Date::today()))
+            };
+            out.push_tasks(&tasks, limit);
         }
-        Command::ListTodo => {
-            println!("Tasks Todo:");
-            todo_list.print_todo();
-            Ok(())
+        Command::ListTodo { sort, limit } => {
+            out.push("Tasks Todo:");
+            let tasks = match sort.unwrap_or(todo_list.default_sort) {
+                Sort::Position => todo_list.todo(),
+                Sort::Urgency => todo_list.sorted_by_urgency(Date::today()),
+            };
+            out.push_tasks(&tasks, limit);
         }
-        Command::ListByPriority { priority } => {
-            println!("Tasks with {} priority:", priority);
-            todo_list.print_by_priority(priority);
-            Ok(())
+        Command::ListByPriority { priority, limit } => {
+            out.push(format!("Tasks with {} priority:", priority));
+            out.push_tasks(&todo_list.by_priority(priority), limit);
+        }
+        Command::Query { expr } => {
+            let clauses = parse_query(&expr)?;
+            out.push("Matching Tasks:");
+            out.push_tasks(&todo_list.query(&clauses), 0);
+        }
+        Command::Today => {
+            out.push("Due today or overdue:");
+            out.push_tasks(&todo_list.due_today_or_overdue(Date::today()), 0);
+        }
+        Command::ListDue => {
+            out.push("Tasks by due date:");
+            let today = Date::today();
+            for task in todo_list.tasks_by_due() {
+                if let Some(due) = task.due {
+                    out.push(format!("{} due {due}", render_task(task, today)));
+                }
+            }
+        }
+        Command::Progress => {
+            out.push(progress_bar(todo_list));
+        }
+        Command::PriorityCounts => {
+            let counts: Vec<String> = todo_list
+                .priority_counts()
+                .iter()
+                .map(|(priority, count)| format!("{}={count}", priority.name()))
+                .collect();
+            out.push(counts.join(" "));
+        }
+        Command::Count { filter } => {
+            out.push(todo_list.count(filter.unwrap_or(Status::All)).to_string());
         }
         Command::Done { id } => {
-            println!("Marking {id} as done...");
+            out.note(format!("Marking {id} as done..."));
             let task = todo_list.mark_done(id)?;
-            println!("Task {} marked as done.", task.id);
-            Ok(())
+            out.note(format!("Task {} marked as done.", task.id));
+            if let Some(created_at) = task.created_at {
+                let elapsed = task.completed_at.unwrap_or(now()) as i64 - created_at as i64;
+                out.note(format!("completed in {}", humanize_duration(elapsed)));
+            }
+        }
+        Command::Toggle { id } => {
+            let task = todo_list.toggle_done(id)?;
+            let state = if task.done { "done" } else { "not done" };
+            out.note(format!("Task {} is now {state}", task.id));
+        }
+        Command::SetPriority { priority, ids } => {
+            let (updated, missing) = todo_list.set_priority_many(&ids, priority);
+            skipped.extend(missing.into_iter().map(|id| todo_list.not_found(id)));
+            // only fail outright when nothing was changed, so the rest is saved
+            if updated.is_empty() {
+                return Err(match skipped.len() {
+                    1 => skipped.remove(0),
+                    _ => TodoError::TasksNotFound(skipped.iter().map(|e| e.to_string()).collect()),
+                });
+            }
+            for id in updated {
+                out.note(format!("Set task {id} to {priority} priority"));
+            }
+            // kept out of quiet mode, the command only partly succeeded
+            for err in skipped {
+                out.push(format!("skipped: {err}"));
+            }
+        }
+        Command::Show { id } => {
+            let task = todo_list.get(id)?;
+            out.push(format!("Task {}:", task.id));
+            for line in task.text.lines() {
+                out.push(format!("  {line}"));
+            }
+            out.push(format!("priority: {}", task.priority.name()));
+            out.push(format!("done: {}", if task.done { "yes" } else { "no" }));
+            if let Some(due) = task.due {
+                out.push(format!("due: {due}"));
+            }
+            if !task.tags.is_empty() {
+                out.push(format!("tags: {}", task.tags.join(", ")));
+            }
+        }
+        Command::Duplicate { id } => {
+            let task = todo_list.duplicate(id)?;
+            out.note(format!("Duplicated task {id} as {}", task.id));
+        }
+        Command::Edit { id, text, append } => {
+            let task = match (text, append) {
+                (_, Some(extra)) => todo_list.append_text(id, &extra)?,
+                (Some(text), None) => todo_list.edit_text(id, text)?,
+                (None, None) => return Err(TodoError::MissingArgument),
+            };
+            out.note(format!("Task {} is now: {}", task.id, task.text));
+        }
+        Command::Tag { id, tags } => {
+            let task = todo_list.tag(id, &tags)?;
+            out.note(format!("Tagged task {} with {}", task.id, tags.join(", ")));
+        }
+        Command::Tags => {
+            let counts = todo_list.tag_counts();
+            if counts.is_empty() {
+                out.push("no tags");
+            }
+            for (tag, count) in counts {
+                out.push(format!("{tag}: {count}"));
+            }
+        }
+        Command::RenameTag { from, to } => {
+            let changed = todo_list.rename_tag(&from, &to)?;
+            out.note(format!(
+                "Renamed {from} to {} on {changed} tasks",
+                to.trim()
+            ));
+        }
+        Command::SetDue { id, date } => {
+            let task = todo_list.set_due(id, date)?;
+            out.note(format!("Set task {} due on {date}", task.id));
+        }
+        Command::Escalate => {
+            let escalated = todo_list.escalate_due_soon(Date::today());
+            out.note(format!("Escalated {escalated} tasks to high priority"));
+        }
+        Command::SetSort { sort } => {
+            todo_list.default_sort = sort;
+            out.note(format!("Default sort set to {sort}"));
+        }
+        Command::Move {
+            id,
+            position,
+            before,
+            after,
+        } => {
+            let task = match (position, before, after) {
+                (_, Some(anchor), _) => todo_list.move_before(id, anchor)?,
+                (_, _, Some(anchor)) => todo_list.move_after(id, anchor)?,
+                (Some(position), _, _) => todo_list.move_to(id, position)?,
+                (None, None, None) => return Err(TodoError::MissingArgument),
+            };
+            out.note(format!("Moved task {}", task.id));
+        }
+        Command::Clone { id, to } => {
+            let target = profile_path(opts.base(), &to)?;
+            if opts.dry_run {
+                todo_list.get(id)?;
+                out.note(format!("Would clone task {id} into {to}"));
+            } else {
+                // the loaded list is saved last, so writing its file here would be lost
+                let new_id = if target == opts.path() {
+                    let task = todo_list.get(id)?.clone();
+                    todo_list.insert(task).id
+                } else {
+                    clone_task(todo_list, id, &target)?
+                };
+                out.note(format!("Cloned task {id} into {to} as {new_id}"));
+            }
+        }
+        Command::Merge { path } => {
+            // load_todo_list reads a missing file as empty, a typo must not
+            if !std::path::Path::new(&path).exists() {
+                return Err(TodoError::LoadError {
+                    path,
+                    source: std::io::ErrorKind::NotFound.into(),
+                });
+            }
+            let merged = todo_list.merge(load_todo_list(&path)?);
+            out.note(format!("Merged {merged} tasks from {path}"));
+        }
+        Command::Snapshot { name } => {
+            if opts.dry_run {
+                out.note(format!("Would save snapshot {name}"));
+            } else {
+                save_snapshot(opts.path(), &name, todo_list)?;
+                out.note(format!("Saved snapshot {name}"));
+            }
+        }
+        Command::RestoreSnapshot { name } => {
+            *todo_list = load_snapshot(opts.path(), &name)?;
+            out.note(format!("Restored snapshot {name}"));
+        }
+        Command::Snapshots => {
+            out.push("Snapshots:");
+            for name in list_snapshots(opts.path()) {
+                out.push(name);
+            }
+        }
+        Command::Verify => {
+            let issues = todo_list.verify();
+            if !issues.is_empty() {
+                return Err(TodoError::VerifyFailed(issues));
+            }
+            out.note("No problems found");
         }
-        Command::SetPriority { id, priority } => {
-            let task = todo_list.set_priority(id, priority)?;
-            println!("Set task {} to {} priority", task.id, task.priority);
-            Ok(())
+        Command::Dedup => {
+            let removed = todo_list.dedup();
+            out.note(format!("Removed {removed} duplicate tasks"));
+        }
+        Command::Archive => {
+            let done = todo_list.take_done();
+            if done.is_empty() {
+                out.note("nothing to archive");
+            } else if opts.dry_run {
+                out.note(format!("Would archive {} tasks", done.len()));
+            } else {
+                append_archive(&archive_path(opts.path()), &done)?;
+                out.note(format!("Archived {} tasks", done.len()));
+            }
+        }
+        Command::ListArchive => {
+            let archive = load_archive(&archive_path(opts.path()))?;
+            out.push("Archived Tasks:");
+            out.push_tasks(&archive.iter().collect::<Vec<_>>(), 0);
+        }
+        Command::Export { format, output } => {
+            let rendered = export(todo_list, format)?;
+            match output {
+                None => rendered.lines().for_each(|line| out.push(line)),
+                Some(path) if opts.dry_run => out.note(format!("Would export to {path}")),
+                Some(path) => {
+                    write_atomic(&path, &rendered).map_err(TodoError::ExportError)?;
+                    out.note(format!(
+                        "Exported {} tasks to {path}",
+                        todo_list.tasks.len()
+                    ));
+                }
+            }
+        }
+        // handled by the binary, it cannot run from inside a command
+        Command::Repl | Command::Watch { .. } => return Err(TodoError::UnknownCommand),
+        Command::Reset { yes } => {
+            if yes || opts.confirmed {
+                todo_list.reset();
+                out.note("Removed all tasks");
+            } else {
+                out.note("Reset cancelled");
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// reads all of `input` as task text, for `todo add -`
+fn read_text(mut input: impl Read) -> Result<String, TodoError> {
+    let mut text = String::new();
+    input
+        .read_to_string(&mut text)
+        .map_err(TodoError::ReadError)?;
+    Ok(text)
+}
+
+/// runs commands read line by line against `todo_list` until `quit` or end
+/// of input; errors are reported and the session carries on
+pub fn run_repl(
+    todo_list: &mut TodoList,
+    mut input: impl BufRead,
+    out: &mut impl Write,
+    opts: &Options,
+) {
+    let _ = write!(out, "> ");
+    let _ = out.flush();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if !matches!(input.read_line(&mut line), Ok(n) if n > 0) {
+            break;
+        }
+        let line = line.trim();
+        if line == "quit" || line == "exit" {
+            break;
+        }
+        if !line.is_empty() {
+            let args = std::iter::once(String::from("todo")).chain(split_args(line));
+            let result = match Cli::try_parse_from(args) {
+                Ok(cli) => {
+                    // the answer is the next line of the session's own input
+                    let confirmed = match &cli.command {
+                        Some(command) if needs_confirmation(command) => {
+                            confirm_reset(&mut input, out).unwrap_or(false)
+                        }
+                        _ => false,
+                    };
+                    let opts = Options {
+                        confirmed,
+                        ..opts.clone()
+                    };
+                    run_repl_line(cli, todo_list, &opts)
+                }
+                Err(e) => {
+                    let _ = write!(out, "{e}");
+                    Ok(Output::default())
+                }
+            };
+            let _ = match result {
+                Ok(output) => write!(out, "{output}"),
+                Err(e) => writeln!(out, "Error: {e}"),
+            };
+        }
+        let _ = write!(out, "> ");
+        let _ = out.flush();
+    }
+    let _ = writeln!(out);
+}
+
+/// runs one parsed REPL line, honouring the flags typed on it on top of the
+/// session's; a `--dry-run` line works on a copy that is thrown away
+fn run_repl_line(cli: Cli, todo_list: &mut TodoList, opts: &Options) -> Result<Output, TodoError> {
+    if cli.profile.is_some() || cli.compact {
+        let mut out = Output::default();
+        out.push("--profile and --compact only apply when starting todo, line ignored");
+        return Ok(out);
+    }
+    let Some(command) = cli.command else {
+        return Ok(Output::default());
+    };
+    let line_opts = Options {
+        dry_run: opts.dry_run || cli.dry_run,
+        by_index: opts.by_index || cli.by_index,
+        quiet: opts.quiet || cli.quiet,
+        in_repl: true,
+        ..opts.clone()
+    };
+    if cli.dry_run {
+        execute_command_with(command, &mut todo_list.clone(), &line_opts)
+    } else {
+        execute_command_with(command, todo_list, &line_opts)
+    }
+}
+
+/// redraws the list stored at `path` each time the file's modification time
+/// changes, checking every `interval_ms`; never returns and never saves
+pub fn run_watch(path: &str, interval_ms: u64, out: &mut impl Write) -> ! {
+    let mut last_seen = None;
+    loop {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if last_seen.is_none() || modified != last_seen.flatten() {
+            last_seen = Some(modified);
+            let _ = write!(out, "{}", watch_frame(path));
+            let _ = out.flush();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(interval_ms));
+    }
+}
+
+/// the screen `run_watch` draws: a clear followed by the list, or the error
+/// that kept it from loading
+fn watch_frame(path: &str) -> String {
+    const CLEAR: &str = "\x1b[2J\x1b[H";
+    match load_todo_list(path) {
+        Ok(todo_list) => {
+            let mut out = Output::default();
+            out.push(format!("Tasks in {path}:"));
+            out.push_tasks(&todo_list.tasks.iter().collect::<Vec<_>>(), 0);
+            format!("{CLEAR}{out}")
         }
+        Err(e) => format!("{CLEAR}Error: {e}\n"),
     }
 }
 
+/// splits a line into arguments on whitespace, keeping quoted text together
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// whether `cmd` only goes ahead once the caller sets `Options::confirmed`
+pub fn needs_confirmation(cmd: &Command) -> bool {
+    matches!(cmd, Command::Reset { yes: false })
+}
+
+/// prompts on `out` and returns whether the line read back was `yes`
+pub fn confirm_reset(input: &mut impl BufRead, out: &mut impl Write) -> Result<bool, TodoError> {
+    let _ = write!(out, "Type yes to remove every task: ");
+    let _ = out.flush();
+    let mut answer = String::new();
+    input
+        .read_line(&mut answer)
+        .map_err(|_| TodoError::ConfirmationRequired)?;
+    Ok(answer.trim() == "yes")
+}
+
+/// copies a task into the list stored at `path`, returning its id there
+pub fn clone_task(todo_list: &TodoList, id: u32, path: &str) -> Result<u32, TodoError> {
+    let task = todo_list.get(id)?.clone();
+    let mut other = load_todo_list(path)?;
+    let new_id = other.insert(task).id;
+    save_todo_list(path, &other)?;
+    Ok(new_id)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,9 +1096,13 @@ mod tests {
     fn test_add() -> Result<(), TodoError> {
         let cmd1 = Command::Add {
             text: String::from("hello there"),
+            after: None,
+            priority: None,
         };
         let cmd2 = Command::Add {
             text: String::from("hello there"),
+            after: None,
+            priority: None,
         };
         let mut task_list: TodoList = Default::default();
         execute_command(cmd1, &mut task_list)?;
@@ -121,10 +1111,27 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_priority_flag() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for args in [
+            &["todo", "add", "plain"][..],
+            &["todo", "add", "urgent thing", "--priority", "high"],
+        ] {
+            let cli = Cli::try_parse_from(args).unwrap();
+            execute_command(cli.command.unwrap(), &mut task_list)?;
+        }
+        assert_eq!(Priority::Low, task_list.get(1)?.priority);
+        assert_eq!(Priority::High, task_list.get(2)?.priority);
+        Ok(())
+    }
+
     #[test]
     fn test_mark_done() -> Result<(), TodoError> {
         let cmd = Command::Add {
             text: String::from("hello there"),
+            after: None,
+            priority: None,
         };
         let mut task_list: TodoList = Default::default();
         execute_command(cmd, &mut task_list)?;
@@ -138,18 +1145,753 @@ mod tests {
     fn test_print_todo_and_done() -> Result<(), TodoError> {
         let cmd1 = Command::Add {
             text: String::from("hello there"),
+            after: None,
+            priority: None,
         };
         let cmd2 = Command::Add {
             text: String::from("goodbye there"),
+            after: None,
+            priority: None,
         };
         let mut task_list: TodoList = Default::default();
         execute_command(cmd1, &mut task_list)?;
         execute_command(cmd2, &mut task_list)?;
-        // TODO: test on stdout instead of like this
         let _ = task_list.mark_done(2)?;
 
-        assert_eq!(1, task_list.tasks.iter().filter(|t| t.done).count());
-        assert_eq!(1, task_list.tasks.iter().filter(|t| !t.done).count());
+        let cmd = Command::ListDone {
+            limit: 0,
+            after: None,
+            before: None,
+        };
+        let done = execute_command(cmd, &mut task_list)?;
+        assert_eq!(vec!["Tasks Done:", "[x] (L) 2: goodbye there"], done.lines);
+        let todo = execute_command(
+            Command::ListTodo {
+                sort: None,
+                limit: 0,
+            },
+            &mut task_list,
+        )?;
+        assert_eq!(vec!["Tasks Todo:", "[ ] (L) 1: hello there"], todo.lines);
+        Ok(())
+    }
+
+    fn list_with(args: &[&str], task_list: &mut TodoList) -> Result<Vec<String>, TodoError> {
+        let cli = Cli::try_parse_from(["todo", "list"].iter().chain(args)).unwrap();
+        Ok(execute_command(cli.command.unwrap(), task_list)?.lines)
+    }
+
+    #[test]
+    fn test_list_only() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("hello there"))?;
+        task_list.add(String::from("goodbye there"))?;
+        task_list.mark_done(2)?;
+
+        let all = vec![
+            "Tasks:",
+            "[ ] (L) 1: hello there",
+            "[x] (L) 2: goodbye there",
+        ];
+        assert_eq!(all, list_with(&[], &mut task_list)?);
+        assert_eq!(all, list_with(&["--only", "all"], &mut task_list)?);
+        assert_eq!(
+            vec!["Tasks:", "[x] (L) 2: goodbye there"],
+            list_with(&["--only", "done"], &mut task_list)?
+        );
+        assert_eq!(
+            vec!["Tasks:", "[ ] (L) 1: hello there"],
+            list_with(&["--only", "todo"], &mut task_list)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiline_text() -> Result<(), TodoError> {
+        let path = "tests/data/multiline_test.json";
+        let text = "line1\nline2 🎉";
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from(text))?;
+        save_todo_list(path, &task_list)?;
+        let loaded = load_todo_list(path);
+        let _ = std::fs::remove_file(path);
+        let mut loaded = loaded?;
+
+        assert_eq!(text, loaded.tasks[0].text);
+        assert_eq!(
+            vec!["Tasks:", "[ ] (L) 1: line1 line2 🎉"],
+            list_with(&[], &mut loaded)?
+        );
+        let shown = execute_command(Command::Show { id: 1 }, &mut loaded)?.lines;
+        assert_eq!(vec!["Task 1:", "  line1", "  line2 🎉"], shown[..3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_hide_done() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.mark_done(1)?;
+        task_list.mark_done(3)?;
+
+        assert_eq!(
+            vec!["Tasks:", "[ ] (L) 2: b", "(2 completed hidden)"],
+            list_with(&["--hide-done"], &mut task_list)?
+        );
+
+        task_list.tasks.retain(|t| !t.done);
+        assert_eq!(
+            vec!["Tasks:", "[ ] (L) 2: b"],
+            list_with(&["--hide-done"], &mut task_list)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_since() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["new", "new done", "old", "legacy"] {
+            task_list.add_with_priority(String::from(text), Priority::High)?;
+        }
+        task_list.mark_done(2)?;
+        task_list.tasks[2].created_at = Some(now() - 10 * 86_400);
+        task_list.tasks[3].created_at = None;
+
+        assert_eq!(
+            vec!["Tasks:", "[ ] (H) 1: new", "[x] (H) 2: new done"],
+            list_with(&["--since", "7d"], &mut task_list)?
+        );
+        assert_eq!(
+            vec!["Tasks:", "[ ] (H) 1: new", "(1 completed hidden)"],
+            list_with(&["--since", "24h", "--hide-done"], &mut task_list)?
+        );
+        assert_eq!(
+            vec!["Tasks:", "[ ] (H) 1: new", "[ ] (H) 3: old"],
+            list_with(
+                &["--since", "2w", "--only", "todo", "--priority", "3"],
+                &mut task_list
+            )?
+        );
+        assert!(Cli::try_parse_from(["todo", "list", "--since", "7"]).is_err());
         Ok(())
     }
+
+    #[test]
+    fn test_list_limit() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c", "d"] {
+            task_list.add(String::from(text))?;
+        }
+
+        assert_eq!(
+            vec!["Tasks:", "[ ] (L) 1: a", "[ ] (L) 2: b", "... and 2 more"],
+            list_with(&["--limit", "2"], &mut task_list)?
+        );
+        assert_eq!(5, list_with(&["--limit", "0"], &mut task_list)?.len());
+        assert_eq!(5, list_with(&["--limit", "4"], &mut task_list)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_priority_counts_output() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.set_priority(2, Priority::Medium)?;
+        task_list.set_priority(3, Priority::Medium)?;
+
+        let out = execute_command(Command::PriorityCounts, &mut task_list)?;
+        assert_eq!(vec!["low=1 medium=2 high=0"], out.lines);
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_overdue() -> Result<(), TodoError> {
+        let today: Date = "2026-10-14".parse()?;
+        let mut task_list: TodoList = Default::default();
+        for text in ["late", "on time", "no due", "done late"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.set_due(1, today.add_days(-1))?;
+        task_list.set_due(2, today)?;
+        task_list.set_due(4, today.add_days(-1))?;
+        task_list.mark_done(4)?;
+
+        let rendered: Vec<String> = task_list
+            .tasks
+            .iter()
+            .map(|t| render_task(t, today))
+            .collect();
+        assert_eq!(
+            vec![
+                "[ ] (L) 1: late (!)",
+                "[ ] (L) 2: on time",
+                "[ ] (L) 3: no due",
+                "[x] (L) 4: done late",
+            ],
+            rendered
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_humanize_duration() {
+        assert_eq!("0s", humanize_duration(0));
+        assert_eq!("0s", humanize_duration(-30));
+        assert_eq!("40s", humanize_duration(40));
+        assert_eq!("2m 5s", humanize_duration(125));
+        assert_eq!("2h 5m", humanize_duration(2 * 3_600 + 5 * 60 + 9));
+        assert_eq!("3d 4h", humanize_duration(3 * 86_400 + 4 * 3_600 + 59));
+    }
+
+    #[test]
+    fn test_progress_bar() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        assert_eq!("[----------] 0% (0/0)", progress_bar(&task_list));
+
+        for text in ["a", "b", "c", "d", "e"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.mark_done(1)?;
+        task_list.mark_done(4)?;
+        assert_eq!("[####------] 40% (2/5)", progress_bar(&task_list));
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_output() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("hello there"))?;
+        task_list.add(String::from("goodbye there"))?;
+        task_list.mark_done(2)?;
+
+        for (filter, expected) in [
+            (None, "2"),
+            (Some(Status::All), "2"),
+            (Some(Status::Done), "1"),
+            (Some(Status::Todo), "1"),
+        ] {
+            let out = execute_command(Command::Count { filter }, &mut task_list)?;
+            assert_eq!(vec![expected], out.lines);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_output() -> Result<(), TodoError> {
+        let path = "tests/data/export_test.md";
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("write report"))?;
+        task_list.add(String::from("file taxes"))?;
+        task_list.mark_done(2)?;
+
+        let cmd = |output| Command::Export {
+            format: ExportFormat::Markdown,
+            output,
+        };
+        let printed = execute_command(cmd(None), &mut task_list)?.lines;
+        let written = execute_command(cmd(Some(String::from(path))), &mut task_list);
+        let contents = std::fs::read_to_string(path);
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(vec!["- [ ] write report", "- [x] file taxes"], printed);
+        assert_eq!(vec![format!("Exported 2 tasks to {path}")], written?.lines);
+        assert_eq!(printed.join("\n") + "\n", contents.unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_nothing() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("hello there"))?;
+
+        let out = execute_command(Command::Archive, &mut task_list)?;
+        assert_eq!(vec!["nothing to archive"], out.lines);
+        assert_eq!(1, task_list.tasks.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run_archive() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("hello there"))?;
+        task_list.mark_done(1)?;
+
+        let opts = Options {
+            dry_run: true,
+            ..Default::default()
+        };
+        let out = execute_command_with(Command::Archive, &mut task_list, &opts)?;
+        assert_eq!(vec!["Would archive 1 tasks"], out.lines);
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_profile_path() -> Result<(), TodoError> {
+        let path = "tests/data/archive_profile_test.json";
+        let archive = archive_path(path);
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("filed"))?;
+        task_list.mark_done(1)?;
+        let opts = Options {
+            path: Some(String::from(path)),
+            ..Default::default()
+        };
+
+        let archived = execute_command_with(Command::Archive, &mut task_list, &opts);
+        let listed = execute_command_with(Command::ListArchive, &mut task_list, &opts);
+        let exists = std::path::Path::new(&archive).is_file();
+        let _ = std::fs::remove_file(&archive);
+
+        archived?;
+        assert!(exists);
+        assert_eq!(vec!["Archived Tasks:", "[x] (L) 1: filed"], listed?.lines);
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshots_per_profile() -> Result<(), TodoError> {
+        let base = "tests/data/snapshot_profile_test.json";
+        let work = profile_path(base, "work")?;
+        let opts = |path: &str| Options {
+            path: Some(path.to_string()),
+            ..Default::default()
+        };
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("home task"))?;
+        let snapshot = |name: &str| Command::Snapshot {
+            name: name.to_string(),
+        };
+
+        let saved = execute_command_with(snapshot("daily"), &mut task_list, &opts(base));
+        let listed_work = execute_command_with(Command::Snapshots, &mut task_list, &opts(&work));
+        let restored = execute_command_with(
+            Command::RestoreSnapshot {
+                name: String::from("daily"),
+            },
+            &mut task_list,
+            &opts(&work),
+        );
+        let dotted = execute_command_with(snapshot("work.daily"), &mut task_list, &opts(base));
+        let _ = std::fs::remove_file(crate::persistence::snapshot_path(base, "daily"));
+
+        saved?;
+        assert_eq!(vec!["Snapshots:"], listed_work?.lines);
+        assert!(matches!(restored, Err(TodoError::SnapshotNotFound)));
+        assert!(matches!(dotted, Err(TodoError::InvalidSnapshotName)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_done_by_index() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.move_to(3, 1)?;
+
+        let opts = Options {
+            by_index: true,
+            ..Default::default()
+        };
+        execute_command_with(Command::Done { id: 1 }, &mut task_list, &opts)?;
+        assert!(task_list.get(3)?.done);
+        assert!(!task_list.get(1)?.done);
+        assert!(matches!(
+            execute_command_with(Command::Done { id: 4 }, &mut task_list, &opts),
+            Err(TodoError::IndexOutOfRange(4))
+        ));
+        assert_eq!(
+            "no task at position 4",
+            TodoError::IndexOutOfRange(4).to_string()
+        );
+
+        execute_command_with(Command::Toggle { id: 2 }, &mut task_list, &opts)?;
+        assert!(task_list.get(1)?.done);
+        let cmd = Command::Move {
+            id: 3,
+            position: None,
+            before: Some(1),
+            after: None,
+        };
+        execute_command_with(cmd, &mut task_list, &opts)?;
+        assert_eq!(2, task_list.id_at_index(1)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quiet_add() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        let opts = Options {
+            quiet: true,
+            ..Default::default()
+        };
+        let cmd = Command::Add {
+            text: String::from("hello there"),
+            after: None,
+            priority: None,
+        };
+        let out = execute_command_with(cmd, &mut task_list, &opts)?;
+        assert_eq!("", out.to_string());
+        assert_eq!(1, task_list.tasks.len());
+
+        let out = execute_command_with(Command::Count { filter: None }, &mut task_list, &opts)?;
+        assert_eq!("1\n", out.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset_with_yes() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("hello there"))?;
+
+        let out = execute_command(Command::Reset { yes: true }, &mut task_list)?;
+        assert_eq!(vec!["Removed all tasks"], out.lines);
+        assert!(task_list.tasks.is_empty());
+        assert_eq!(1, task_list.next_id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset_confirmation() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("hello there"))?;
+        assert!(needs_confirmation(&Command::Reset { yes: false }));
+        assert!(!needs_confirmation(&Command::Reset { yes: true }));
+
+        let out = execute_command(Command::Reset { yes: false }, &mut task_list)?;
+        assert_eq!(vec!["Reset cancelled"], out.lines);
+        assert_eq!(1, task_list.tasks.len());
+
+        let mut prompt = Vec::new();
+        assert!(!confirm_reset(&mut "no\n".as_bytes(), &mut prompt)?);
+        assert!(confirm_reset(&mut " yes \n".as_bytes(), &mut prompt)?);
+        let opts = Options {
+            confirmed: true,
+            ..Default::default()
+        };
+        execute_command_with(Command::Reset { yes: false }, &mut task_list, &opts)?;
+        assert!(task_list.tasks.is_empty());
+
+        let input = "add a\nreset\nno\nadd b\nreset\nyes\n";
+        let mut out = Vec::new();
+        run_repl(
+            &mut task_list,
+            input.as_bytes(),
+            &mut out,
+            &Options::default(),
+        );
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Type yes to remove every task: Reset cancelled"));
+        assert!(out.contains("Removed all tasks"));
+        assert!(task_list.tasks.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_from_input() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        let text = read_text("  a long task\nspanning lines\n".as_bytes())?;
+        let task = task_list.add(text)?;
+        assert_eq!("a long task\nspanning lines", task.text);
+
+        let empty = read_text("\n  \n".as_bytes())?;
+        assert!(matches!(task_list.add(empty), Err(TodoError::EmptyTask)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_found_hint() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        let hint = |task_list: &TodoList, id| task_list.not_found(id).to_string();
+        assert_eq!("task 15 not found", hint(&task_list, 15));
+
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+        assert_eq!("task 15 not found; did you mean 3?", hint(&task_list, 15));
+        task_list.tasks.remove(1);
+        assert_eq!(
+            "task 2 not found; did you mean 1 or 3?",
+            hint(&task_list, 2)
+        );
+        let err = execute_command(Command::Done { id: 2 }, &mut task_list).unwrap_err();
+        assert_eq!("task 2 not found; did you mean 1 or 3?", err.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_priority_partial() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("hello there"))?;
+        task_list.add(String::from("goodbye there"))?;
+
+        let cli = Cli::try_parse_from(["todo", "set-priority", "high", "2", "5", "1"]).unwrap();
+        let out = execute_command(cli.command.unwrap(), &mut task_list)?;
+        assert_eq!(
+            vec![
+                "Set task 2 to (H) priority",
+                "Set task 1 to (H) priority",
+                "skipped: task 5 not found; did you mean 2?",
+            ],
+            out.lines
+        );
+
+        let cmd = Command::SetPriority {
+            priority: Priority::Low,
+            ids: vec![5, 6],
+        };
+        let err = execute_command(cmd, &mut task_list).unwrap_err();
+        assert_eq!(
+            "none of the tasks were found:\ntask 5 not found; did you mean 2?\ntask 6 not found; did you mean 2?",
+            err.to_string()
+        );
+
+        let opts = Options {
+            by_index: true,
+            ..Default::default()
+        };
+        let cmd = Command::SetPriority {
+            priority: Priority::Medium,
+            ids: vec![3, 1, 9],
+        };
+        let out = execute_command_with(cmd, &mut task_list, &opts)?;
+        assert_eq!(
+            vec![
+                "Set task 1 to (M) priority",
+                "skipped: no task at position 3",
+                "skipped: no task at position 9",
+            ],
+            out.lines
+        );
+        let cmd = Command::SetPriority {
+            priority: Priority::Low,
+            ids: vec![4],
+        };
+        assert!(matches!(
+            execute_command_with(cmd, &mut task_list, &opts),
+            Err(TodoError::IndexOutOfRange(4))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_frame() {
+        let frame = watch_frame("tests/data/test.json");
+        assert!(frame.starts_with("\x1b[2J\x1b[H"));
+        assert_eq!(4, frame.lines().count());
+        assert!(watch_frame("tests/data").contains("Error: "));
+    }
+
+    #[test]
+    fn test_verify_output() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("a"))?;
+        task_list.add(String::from("b"))?;
+        assert_eq!(
+            vec!["No problems found"],
+            execute_command(Command::Verify, &mut task_list)?.lines
+        );
+
+        task_list.tasks[1].id = 1;
+        let err = execute_command(Command::Verify, &mut task_list).unwrap_err();
+        assert_eq!(
+            "found 1 problem in the todo list:\ntask 1: id is used more than once",
+            err.to_string()
+        );
+        let err = TodoError::VerifyFailed(vec![String::from("a"), String::from("b")]);
+        assert!(err.to_string().starts_with("found 2 problems"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            vec!["add", "walk the dog", "--after", "2"],
+            split_args(r#"add "walk the dog"  --after 2"#)
+        );
+        assert_eq!(vec!["add", "it's", ""], split_args(r#"add "it's" ''"#));
+    }
+
+    #[test]
+    fn test_repl() {
+        let input = "add \"hello there\"\n\nbogus\ndone 7\nadd -\nquit\nadd ignored\n";
+        let mut task_list: TodoList = Default::default();
+        let mut out = Vec::new();
+        run_repl(
+            &mut task_list,
+            input.as_bytes(),
+            &mut out,
+            &Options::default(),
+        );
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(1, task_list.tasks.len());
+        assert_eq!("hello there", task_list.tasks[0].text);
+        assert!(out.contains("Added hello there as 1 with (L) priority"));
+        assert!(out.contains("unrecognized subcommand 'bogus'"));
+        assert!(out.contains("Error: the repl reads commands from stdin"));
+        assert!(out.contains("Error: task 7 not found; did you mean 1?"));
+    }
+
+    #[test]
+    fn test_repl_line_flags() {
+        let input = "add a\n--dry-run add b\n-q add c\nadd d\nmove 3 1\n--by-index done 1\n--profile x list\n";
+        let mut task_list: TodoList = Default::default();
+        let mut out = Vec::new();
+        run_repl(
+            &mut task_list,
+            input.as_bytes(),
+            &mut out,
+            &Options::default(),
+        );
+
+        let out = String::from_utf8(out).unwrap();
+        let texts: Vec<&str> = task_list.tasks.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(vec!["d", "a", "c"], texts);
+        assert!(out.contains("Added b as 2"));
+        assert!(!out.contains("Added c"));
+        assert!(task_list.get(3).is_ok_and(|t| t.done));
+        assert!(out.contains("--profile and --compact only apply when starting todo"));
+    }
+
+    #[test]
+    fn test_clone_task() -> Result<(), TodoError> {
+        let path = "tests/data/clone_test.json";
+        let mut other: TodoList = Default::default();
+        other.add(String::from("already here"))?;
+        save_todo_list(path, &other)?;
+
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("water plants"))?;
+        let new_id = clone_task(&task_list, 1, path)?;
+
+        let cloned = load_todo_list(path);
+        let _ = std::fs::remove_file(path);
+        let cloned = cloned?;
+        assert_eq!(2, new_id);
+        assert_eq!(2, cloned.tasks.len());
+        assert_eq!("water plants", cloned.get(2)?.text);
+        assert_eq!("water plants", task_list.get(1)?.text);
+        assert_eq!(1, task_list.tasks.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_clone_command() -> Result<(), TodoError> {
+        let base = "tests/data/clone_profile_test.json";
+        let (work, home) = (profile_path(base, "work")?, profile_path(base, "home")?);
+        let opts = Options {
+            path: Some(work.clone()),
+            base: Some(base.to_string()),
+            ..Default::default()
+        };
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("water plants"))?;
+        let clone = |to: &str| Command::Clone {
+            id: 1,
+            to: to.to_string(),
+        };
+
+        let into_work = execute_command_with(clone("work"), &mut task_list, &opts);
+        let into_home = execute_command_with(clone("home"), &mut task_list, &opts);
+        let work_written = std::path::Path::new(&work).exists();
+        let home_list = load_todo_list(&home);
+        let _ = std::fs::remove_file(&home);
+
+        assert_eq!(vec!["Cloned task 1 into work as 2"], into_work?.lines);
+        assert_eq!(vec!["Cloned task 1 into home as 1"], into_home?.lines);
+        assert!(!work_written);
+        assert!(matches!(
+            execute_command_with(clone("../evil"), &mut task_list, &opts),
+            Err(TodoError::InvalidProfileName)
+        ));
+        assert_eq!(2, task_list.tasks.len());
+        assert_eq!("water plants", home_list?.get(1)?.text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_missing_file() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        let cmd = Command::Merge {
+            path: String::from("tests/data/nope.json"),
+        };
+        match execute_command(cmd, &mut task_list) {
+            Err(TodoError::LoadError { source, .. }) => {
+                assert_eq!(std::io::ErrorKind::NotFound, source.kind())
+            }
+            _ => panic!("expected merging a missing file to fail"),
+        }
+
+        let cmd = Command::Merge {
+            path: String::from("tests/data/test.json"),
+        };
+        let out = execute_command(cmd, &mut task_list)?;
+        assert_eq!(vec!["Merged 3 tasks from tests/data/test.json"], out.lines);
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_source() {
+        use std::error::Error;
+
+        let parse_error = "x".parse::<u32>().unwrap_err();
+        let err: Box<dyn Error> = Box::new(TodoError::from(parse_error));
+        assert!(err.source().is_some());
+        assert!(
+            TodoError::TaskNotFound {
+                id: 1,
+                closest: Vec::new()
+            }
+            .source()
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let parse_error = "x".parse::<u32>().unwrap_err();
+        let io_error = std::io::Error::other("disk full");
+
+        assert_eq!(2, TodoError::InvalidId(parse_error).exit_code());
+        assert_eq!(
+            3,
+            TodoError::TaskNotFound {
+                id: 1,
+                closest: vec![2]
+            }
+            .exit_code()
+        );
+        assert_eq!(4, TodoError::SaveError(io_error).exit_code());
+        assert_eq!(5, TodoError::UnknownCommand.exit_code());
+        assert_eq!(6, TodoError::MissingArgument.exit_code());
+        assert_eq!(7, TodoError::PriorityError.exit_code());
+        assert_eq!(8, TodoError::InvalidDate.exit_code());
+        assert_eq!(9, TodoError::SnapshotNotFound.exit_code());
+        assert_eq!(10, TodoError::EmptyTask.exit_code());
+        assert_eq!(11, TodoError::ConfirmationRequired.exit_code());
+        let io_error = std::io::Error::other("closed");
+        assert_eq!(12, TodoError::ReadError(io_error).exit_code());
+        let path = String::from("todo.json");
+        let source = std::io::Error::other("denied");
+        assert_eq!(13, TodoError::LoadError { path, source }.exit_code());
+        let json_error = serde_json::from_str::<u32>("x").unwrap_err();
+        assert_eq!(13, TodoError::ParseError(json_error).exit_code());
+        let message = String::from("unknown key");
+        assert_eq!(14, TodoError::QueryError(message).exit_code());
+        let io_error = std::io::Error::other("read-only");
+        assert_eq!(15, TodoError::ExportError(io_error).exit_code());
+        assert_eq!(16, TodoError::InvalidDuration.exit_code());
+        assert_eq!(17, TodoError::VerifyFailed(Vec::new()).exit_code());
+        assert_eq!(18, TodoError::UnsupportedVersion(9).exit_code());
+        assert_eq!(19, TodoError::InvalidSnapshotName.exit_code());
+        assert_eq!(20, TodoError::InvalidProfileName.exit_code());
+        assert_eq!(21, TodoError::StdinInRepl.exit_code());
+        assert_eq!(22, TodoError::IndexOutOfRange(0).exit_code());
+        assert_eq!(3, TodoError::TasksNotFound(Vec::new()).exit_code());
+    }
 }
@@ -0,0 +1,234 @@
+use crate::cli::TodoError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SECONDS_PER_HOUR: u64 = 3_600;
+pub const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+
+/// a calendar day in UTC, stored as days since 1970-01-01
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    days: i64,
+}
+
+impl Date {
+    /// `None` for a day that doesn't exist or a year outside 0 to 9999
+    pub fn from_ymd(year: i64, month: u32, day: u32) -> Option<Date> {
+        if !(0..=9999).contains(&year)
+            || !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+        {
+            return None;
+        }
+        // days_from_civil, see http://howardhinnant.github.io/date_algorithms.html
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let m = month as i64;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        Some(Date {
+            days: era * 146_097 + doe - 719_468,
+        })
+    }
+
+    pub fn ymd(self) -> (i64, u32, u32) {
+        let z = self.days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year, month, day)
+    }
+
+    pub fn from_timestamp(seconds: u64) -> Date {
+        Date {
+            days: (seconds / SECONDS_PER_DAY) as i64,
+        }
+    }
+
+    /// unix seconds at the start of this day
+    pub fn timestamp(self) -> i64 {
+        self.days * SECONDS_PER_DAY as i64
+    }
+
+    pub fn today() -> Date {
+        Date::from_timestamp(now())
+    }
+
+    pub fn add_days(self, days: i64) -> Date {
+        Date {
+            days: self.days + days,
+        }
+    }
+}
+
+/// parses a due date given as `YYYY-MM-DD`, `today`, `tomorrow` or an
+/// offset from today like `+3d` or `+2w`
+pub fn parse_due(s: &str) -> Result<Date, TodoError> {
+    parse_due_from(s, Date::today())
+}
+
+/// `parse_due`, resolving relative dates against `today`
+pub fn parse_due_from(s: &str, today: Date) -> Result<Date, TodoError> {
+    let s = s.trim();
+    match s {
+        "today" => Ok(today),
+        "tomorrow" => Ok(today.add_days(1)),
+        _ => match s.strip_prefix('+') {
+            Some(offset) => match parse_duration(offset) {
+                Some(seconds) if seconds % SECONDS_PER_DAY == 0 => {
+                    Ok(today.add_days((seconds / SECONDS_PER_DAY) as i64))
+                }
+                _ => Err(TodoError::InvalidDate),
+            },
+            None => s.parse(),
+        },
+    }
+}
+
+/// parses a length of time like `24h`, `7d` or `2w` into seconds
+pub fn parse_duration(s: &str) -> Option<u64> {
+    let unit = match s.chars().last()? {
+        'h' => SECONDS_PER_HOUR,
+        'd' => SECONDS_PER_DAY,
+        'w' => 7 * SECONDS_PER_DAY,
+        _ => return None,
+    };
+    let count = &s[..s.len() - 1];
+    if count.is_empty() || !count.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let count: u64 = count.parse().ok()?;
+    count.checked_mul(unit)
+}
+
+/// `parse_duration` for command line arguments
+pub fn parse_duration_arg(s: &str) -> Result<u64, TodoError> {
+    parse_duration(s).ok_or(TodoError::InvalidDuration)
+}
+
+/// seconds since the unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl FromStr for Date {
+    type Err = TodoError;
+
+    /// parses `YYYY-MM-DD`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(3, '-');
+        let (Some(year), Some(month), Some(day)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(TodoError::InvalidDate);
+        };
+        let year = year.parse().map_err(|_| TodoError::InvalidDate)?;
+        let month = month.parse().map_err(|_| TodoError::InvalidDate)?;
+        let day = day.parse().map_err(|_| TodoError::InvalidDate)?;
+        Date::from_ymd(year, month, day).ok_or(TodoError::InvalidDate)
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ymd_round_trip() {
+        for (year, month, day) in [(1970, 1, 1), (2000, 2, 29), (2024, 12, 31), (1969, 7, 20)] {
+            let date = Date::from_ymd(year, month, day).unwrap();
+            assert_eq!((year, month, day), date.ymd());
+        }
+        assert_eq!(Date::from_timestamp(0), Date::from_ymd(1970, 1, 1).unwrap());
+        let date = Date::from_ymd(2026, 10, 14).unwrap();
+        assert_eq!(date, Date::from_timestamp(date.timestamp() as u64 + 3_600));
+    }
+
+    #[test]
+    fn test_parse() -> Result<(), TodoError> {
+        let date: Date = "2026-02-28".parse()?;
+        assert_eq!("2026-03-01", date.add_days(1).to_string());
+        assert!("2026-02-29".parse::<Date>().is_err());
+        assert!("2026-13-01".parse::<Date>().is_err());
+        assert!("tomorrow".parse::<Date>().is_err());
+        assert!("9223372036854775807-01-01".parse::<Date>().is_err());
+        assert!("10000-01-01".parse::<Date>().is_err());
+        assert_eq!("9999-12-31", "9999-12-31".parse::<Date>()?.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_due() -> Result<(), TodoError> {
+        let today: Date = "2026-10-14".parse()?;
+        let due = |s| parse_due_from(s, today).map(|d| d.to_string());
+
+        assert_eq!("2026-10-14", due("today")?);
+        assert_eq!("2026-10-15", due("tomorrow")?);
+        assert_eq!("2026-10-17", due("+3d")?);
+        assert_eq!("2026-10-28", due("+2w")?);
+        assert_eq!("2026-12-25", due("2026-12-25")?);
+        for bad in [
+            "",
+            "yesterday",
+            "+3",
+            "+d",
+            "+-1d",
+            "++1d",
+            "+12h",
+            "3d",
+            "2026-10-32",
+        ] {
+            assert!(matches!(due(bad), Err(TodoError::InvalidDate)), "{bad}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(Some(24 * 3_600), parse_duration("24h"));
+        assert_eq!(Some(7 * 86_400), parse_duration("7d"));
+        assert_eq!(Some(14 * 86_400), parse_duration("2w"));
+        assert_eq!(None, parse_duration("7"));
+        assert_eq!(None, parse_duration("d"));
+        assert_eq!(None, parse_duration("7m"));
+    }
+}
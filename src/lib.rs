@@ -1,3 +1,5 @@
 pub mod cli;
+pub mod date;
 pub mod model;
 pub mod persistence;
+pub mod query;
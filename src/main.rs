@@ -1,19 +1,57 @@
 use clap::Parser;
-use todo::cli::{Cli, TodoError, execute_command};
-use todo::persistence::{PATH, load_todo_list, save_todo_list};
+use std::io::IsTerminal;
+use todo::cli::{
+    Cli, Command, TodoError, confirm_reset, execute_command_with, needs_confirmation, run_repl,
+    run_watch,
+};
+use todo::persistence::{DEFAULT_BACKUPS, JsonStyle, load_todo_list, save_with_backups};
 
 fn main() {
     if let Err(e) = run_todo() {
         eprintln!("Error: {e}");
-        std::process::exit(1);
+        std::process::exit(e.exit_code());
     }
 }
 
 fn run_todo() -> Result<(), TodoError> {
     let cli = Cli::parse();
-    let mut task_list = load_todo_list(PATH);
-    execute_command(cli.command, &mut task_list)?;
-    // save
-    save_todo_list(PATH, &task_list)?;
-    Ok(())
+    let mut opts = cli.options()?;
+    let path = opts.path().to_string();
+    if let Some(Command::Watch { interval }) = cli.command {
+        run_watch(&path, interval, &mut std::io::stdout());
+    }
+    let loaded = load_todo_list(&path)?;
+    let mut task_list = loaded.clone();
+    match cli.command {
+        None | Some(Command::Repl) => {
+            let input = std::io::BufReader::new(std::io::stdin());
+            run_repl(&mut task_list, input, &mut std::io::stdout(), &opts);
+        }
+        Some(command) => {
+            if needs_confirmation(&command) {
+                let stdin = std::io::stdin();
+                if !stdin.is_terminal() {
+                    return Err(TodoError::ConfirmationRequired);
+                }
+                opts.confirmed = confirm_reset(&mut stdin.lock(), &mut std::io::stdout())?;
+            }
+            let output = execute_command_with(command, &mut task_list, &opts)?;
+            print!("{output}");
+        }
+    }
+    // saving an unchanged list would only push older backups out
+    if task_list == loaded {
+        return Ok(());
+    }
+    if opts.dry_run {
+        // on stderr so listings and exports on stdout stay clean
+        eprintln!("(dry run, no changes saved)");
+        return Ok(());
+    }
+    let style = if cli.compact {
+        JsonStyle::Compact
+    } else {
+        JsonStyle::Pretty
+    };
+    save_with_backups(&path, &task_list, style, DEFAULT_BACKUPS)
 }
@@ -1,95 +1,598 @@
 use crate::cli::TodoError;
+use crate::date::{Date, now};
+use crate::query::Clause;
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::str::FromStr;
 
-#[derive(Default, Clone, Deserialize, Serialize)]
+#[derive(Default, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Task {
     pub id: u32,
     pub text: String,
     pub done: bool,
     pub priority: Priority,
+    #[serde(default)]
+    pub due: Option<Date>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// unix seconds, missing on tasks saved before it was recorded
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// unix seconds the task was marked done, missing on older tasks
+    #[serde(default)]
+    pub completed_at: Option<u64>,
+}
+
+impl Task {
+    pub fn is_overdue(&self, today: Date) -> bool {
+        !self.done && self.due.is_some_and(|due| due < today)
+    }
+
+    /// whether the task was created no more than `seconds` before `now`
+    pub fn created_within(&self, seconds: u64, now: u64) -> bool {
+        self.created_at
+            .is_some_and(|created| created <= now && now - created <= seconds)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// adds a tag unless the task already has it, ignoring case
+    pub fn add_tag(&mut self, tag: &str) {
+        if !self.has_tag(tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    /// whether every clause holds for this task
+    pub fn matches(&self, clauses: &[Clause]) -> bool {
+        clauses.iter().all(|clause| match clause {
+            Clause::Priority(priority) => self.priority == *priority,
+            Clause::Done(done) => self.done == *done,
+            Clause::Tag(tag) => self.has_tag(tag),
+            Clause::Text(text) => self.text.to_lowercase().contains(text),
+        })
+    }
+
+    /// checks the task's own invariants, describing each one that is broken
+    pub fn validate(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if self.id == 0 {
+            issues.push(String::from("id must be greater than 0"));
+        }
+        if self.text.trim().is_empty() {
+            issues.push(String::from("text is empty"));
+        }
+        if let (Some(due), Some(created_at)) = (self.due, self.created_at)
+            && due < Date::from_timestamp(created_at)
+        {
+            issues.push(String::from("due date is before the task was created"));
+        }
+        issues
+    }
 }
 
 impl std::fmt::Display for Task {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let status = if self.done { "[x]" } else { "[ ]" };
-        write!(f, "{status} {} {}: {}", self.priority, self.id, self.text)
+        // listings stay one line per task, `show` prints the text as stored
+        let text = self.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        write!(f, "{status} {} {}: {text}", self.priority, self.id)?;
+        for tag in &self.tags {
+            write!(f, " #{tag}")?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Deserialize, Serialize)]
+/// format version written by this build, see `persistence::migrate`
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct TodoList {
+    /// missing in files written before versioning, which read as 0
+    #[serde(default)]
+    pub version: u32,
     pub tasks: Vec<Task>,
     pub next_id: u32,
+    #[serde(default)]
+    pub default_sort: Sort,
 }
 
 impl Default for TodoList {
     fn default() -> Self {
         Self {
+            version: SCHEMA_VERSION,
             tasks: Default::default(),
             next_id: 1,
+            default_sort: Sort::default(),
         }
     }
 }
 
 impl TodoList {
-    pub fn add(&mut self, text: String) -> &Task {
-        let id = self.next_id;
-        self.tasks.push(Task {
-            id,
-            text,
-            done: false,
-            priority: Priority::default(),
-        });
-        self.next_id = id + 1;
+    /// adds a task with surrounding whitespace trimmed, rejecting blank text
+    pub fn add(&mut self, text: String) -> Result<&Task, TodoError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(TodoError::EmptyTask);
+        }
+        Ok(self.insert(Task {
+            text: text.to_string(),
+            created_at: Some(now()),
+            ..Default::default()
+        }))
+    }
+
+    pub fn add_with_priority(
+        &mut self,
+        text: String,
+        priority: Priority,
+    ) -> Result<&Task, TodoError> {
+        let id = self.add(text)?.id;
+        self.set_priority(id, priority)
+    }
+
+    /// adds a task placed directly after the task with id `anchor`
+    pub fn add_after(&mut self, text: String, anchor: u32) -> Result<&Task, TodoError> {
+        self.index_of(anchor)?;
+        let id = self.add(text)?.id;
+        self.move_after(id, anchor)
+    }
+
+    /// appends a task under a fresh id, ignoring whatever id it carried
+    pub fn insert(&mut self, mut task: Task) -> &Task {
+        task.id = self.next_id;
+        self.next_id += 1;
+        self.tasks.push(task);
         self.tasks.last().unwrap()
     }
 
-    pub fn print_list(&self) {
+    /// validates every task, prefixing each issue with the task's id
+    pub fn validate(&self) -> Vec<String> {
+        self.tasks
+            .iter()
+            .flat_map(|t| {
+                t.validate()
+                    .into_iter()
+                    .map(move |issue| format!("task {}: {issue}", t.id))
+            })
+            .collect()
+    }
+
+    /// everything `validate` reports plus problems with the ids themselves,
+    /// empty when the list is healthy
+    pub fn verify(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        let mut seen = HashMap::new();
         for task in &self.tasks {
-            println!("{task}");
+            let count = seen.entry(task.id).or_insert(0);
+            *count += 1;
+            if *count == 2 {
+                issues.push(format!("task {}: id is used more than once", task.id));
+            }
+            if task.id >= self.next_id {
+                issues.push(format!(
+                    "task {}: id is not below next_id {}",
+                    task.id, self.next_id
+                ));
+            }
+        }
+        issues.extend(self.validate());
+        issues
+    }
+
+    /// removes tasks whose trimmed text matches another's, ignoring case,
+    /// keeping the lowest id and marking it done if any copy was done
+    pub fn dedup(&mut self) -> usize {
+        let key = |t: &Task| t.text.trim().to_lowercase();
+        let mut groups: HashMap<String, (u32, bool)> = HashMap::new();
+        for task in &self.tasks {
+            let group = groups.entry(key(task)).or_insert((task.id, false));
+            group.0 = group.0.min(task.id);
+            group.1 |= task.done;
+        }
+        let before = self.tasks.len();
+        self.tasks.retain(|t| groups[&key(t)].0 == t.id);
+        for task in &mut self.tasks {
+            task.done |= groups[&key(task)].1;
+        }
+        before - self.tasks.len()
+    }
+
+    /// removes and returns the done tasks, leaving `next_id` alone
+    pub fn take_done(&mut self) -> Vec<Task> {
+        let (done, todo) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|t| t.done);
+        self.tasks = todo;
+        done
+    }
+
+    /// removes every task and starts ids from 1 again
+    pub fn reset(&mut self) {
+        self.tasks.clear();
+        self.next_id = 1;
+    }
+
+    /// adds a fresh, not-done copy of a task's text and priority
+    pub fn duplicate(&mut self, id: u32) -> Result<&Task, TodoError> {
+        let original = self.get(id)?;
+        let copy = Task {
+            text: original.text.clone(),
+            priority: original.priority,
+            created_at: Some(now()),
+            ..Default::default()
+        };
+        Ok(self.insert(copy))
+    }
+
+    /// id of the task at a 1-based position in list order
+    pub fn id_at_index(&self, index: usize) -> Result<u32, TodoError> {
+        index
+            .checked_sub(1)
+            .and_then(|i| self.tasks.get(i))
+            .map(|t| t.id)
+            .ok_or(TodoError::IndexOutOfRange(index))
+    }
+
+    /// appends every task of `other` under fresh ids, returning how many
+    pub fn merge(&mut self, other: TodoList) -> usize {
+        let count = other.tasks.len();
+        for task in other.tasks {
+            self.insert(task);
+        }
+        count
+    }
+
+    pub fn get(&self, id: u32) -> Result<&Task, TodoError> {
+        self.index_of(id).map(|i| &self.tasks[i])
+    }
+
+    fn get_mut(&mut self, id: u32) -> Result<&mut Task, TodoError> {
+        let i = self.index_of(id)?;
+        Ok(&mut self.tasks[i])
+    }
+
+    /// the error for a missing `id`, naming the closest ids that do exist
+    pub fn not_found(&self, id: u32) -> TodoError {
+        TodoError::TaskNotFound {
+            id,
+            closest: self.closest_ids(id),
         }
     }
 
     pub fn mark_done(&mut self, id: u32) -> Result<&Task, TodoError> {
-        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+        let task = self.get_mut(id)?;
+        if !task.done {
             task.done = true;
-            Ok(task)
-        } else {
-            Err(TodoError::TaskNotFound)
+            task.completed_at = Some(now());
         }
+        Ok(task)
     }
 
-    pub fn print_done(&self) {
-        for task in self.tasks.iter().filter(|t| t.done) {
-            println!("{task}");
+    /// tasks matching every given filter; `None` leaves that axis unfiltered
+    pub fn filter(&self, done: Option<bool>, priority: Option<Priority>) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|t| done.is_none_or(|done| t.done == done))
+            .filter(|t| priority.is_none_or(|priority| t.priority == priority))
+            .collect()
+    }
+
+    /// flips whether a task is done, recording or clearing when it was completed
+    pub fn toggle_done(&mut self, id: u32) -> Result<&Task, TodoError> {
+        let task = self.get_mut(id)?;
+        task.done = !task.done;
+        task.completed_at = task.done.then(now);
+        Ok(task)
+    }
+
+    /// done tasks completed on a day from `start` to `end`, both included
+    pub fn completed_between(&self, start: Date, end: Date) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|t| t.done)
+            .filter(|t| {
+                t.completed_at
+                    .map(Date::from_timestamp)
+                    .is_some_and(|day| start <= day && day <= end)
+            })
+            .collect()
+    }
+
+    /// tasks created in the `seconds` before `now`, skipping ones with no
+    /// creation time
+    pub fn created_since(&self, seconds: u64, now: u64) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|t| t.created_within(seconds, now))
+            .collect()
+    }
+
+    pub fn count(&self, status: Status) -> usize {
+        self.filter(status.done_filter(), None).len()
+    }
+
+    /// fraction of tasks that are done, 0.0 for an empty list
+    pub fn completion_rate(&self) -> f64 {
+        if self.tasks.is_empty() {
+            return 0.0;
         }
+        self.done().len() as f64 / self.tasks.len() as f64
+    }
+
+    /// number of tasks at each priority, lowest first
+    pub fn priority_counts(&self) -> Vec<(Priority, usize)> {
+        Priority::all()
+            .into_iter()
+            .map(|priority| (priority, self.by_priority(priority).len()))
+            .collect()
+    }
+
+    pub fn query(&self, clauses: &[Clause]) -> Vec<&Task> {
+        self.tasks.iter().filter(|t| t.matches(clauses)).collect()
+    }
+
+    pub fn done(&self) -> Vec<&Task> {
+        self.filter(Some(true), None)
     }
 
-    pub fn print_todo(&self) {
-        for task in self.tasks.iter().filter(|t| !t.done) {
-            println!("{task}");
+    pub fn todo(&self) -> Vec<&Task> {
+        self.filter(Some(false), None)
+    }
+
+    pub fn by_priority(&self, priority: Priority) -> Vec<&Task> {
+        self.filter(None, Some(priority))
+    }
+
+    /// sets the priority of every task it can find, returning the ids that
+    /// were updated and the ids that do not exist
+    pub fn set_priority_many(&mut self, ids: &[u32], priority: Priority) -> (Vec<u32>, Vec<u32>) {
+        ids.iter()
+            .partition(|&&id| self.set_priority(id, priority).is_ok())
+    }
+
+    pub fn set_priority(&mut self, id: u32, priority: Priority) -> Result<&Task, TodoError> {
+        let task = self.get_mut(id)?;
+        task.priority = priority;
+        Ok(task)
+    }
+
+    pub fn edit_text(&mut self, id: u32, text: String) -> Result<&Task, TodoError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(TodoError::EmptyTask);
         }
+        let task = self.get_mut(id)?;
+        task.text = text.to_string();
+        Ok(task)
     }
 
-    pub fn print_by_priority(&self, priority: Priority) {
-        for task in self.tasks.iter().filter(|t| t.priority == priority) {
-            println!("{task}");
+    /// appends `extra` to a task's text, separated by a space
+    pub fn append_text(&mut self, id: u32, extra: &str) -> Result<&Task, TodoError> {
+        let extra = extra.trim();
+        if extra.is_empty() {
+            return Err(TodoError::EmptyTask);
         }
+        let task = self.get_mut(id)?;
+        task.text = format!("{} {extra}", task.text);
+        Ok(task)
     }
 
-    pub fn set_priority(&mut self, id: u32, priority: Priority) -> Result<&Task, TodoError> {
-        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            task.priority = priority;
-            Ok(task)
-        } else {
-            Err(TodoError::TaskNotFound)
+    pub fn tag(&mut self, id: u32, tags: &[String]) -> Result<&Task, TodoError> {
+        let task = self.get_mut(id)?;
+        for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            task.add_tag(tag);
+        }
+        Ok(task)
+    }
+
+    pub fn tagged(&self, tag: &str) -> Vec<&Task> {
+        self.tasks.iter().filter(|t| t.has_tag(tag)).collect()
+    }
+
+    /// renames a tag on every task carrying it, returning how many changed;
+    /// tasks that already have `to` just lose `from`
+    pub fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize, TodoError> {
+        let to = to.trim();
+        if to.is_empty() {
+            return Err(TodoError::MissingArgument);
+        }
+        let mut changed = 0;
+        for task in &mut self.tasks {
+            let Some(index) = task.tags.iter().position(|t| t.eq_ignore_ascii_case(from)) else {
+                continue;
+            };
+            if task.has_tag(to) && !from.eq_ignore_ascii_case(to) {
+                task.tags.remove(index);
+            } else {
+                task.tags[index] = to.to_string();
+            }
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    /// every distinct tag in use, lowercased and sorted
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .tasks
+            .iter()
+            .flat_map(|t| t.tags.iter().map(|tag| tag.to_lowercase()))
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// every tag with how many tasks carry it, most used first, ties by name
+    pub fn tag_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = self
+            .all_tags()
+            .into_iter()
+            .map(|tag| {
+                let count = self.tagged(&tag).len();
+                (tag, count)
+            })
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+
+    pub fn set_due(&mut self, id: u32, due: Date) -> Result<&Task, TodoError> {
+        let task = self.get_mut(id)?;
+        task.due = Some(due);
+        Ok(task)
+    }
+
+    /// incomplete tasks, overdue first, then by priority, then by due date
+    pub fn sorted_by_urgency(&self, today: Date) -> Vec<&Task> {
+        let mut tasks = self.todo();
+        tasks.sort_by_key(|t| {
+            (
+                !t.is_overdue(today),
+                Reverse(t.priority),
+                t.due.is_none(),
+                t.due,
+            )
+        });
+        tasks
+    }
+
+    /// incomplete tasks due on or before `today`, by due date then priority
+    pub fn due_today_or_overdue(&self, today: Date) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self
+            .todo()
+            .into_iter()
+            .filter(|t| t.due.is_some_and(|due| due <= today))
+            .collect();
+        tasks.sort_by_key(|t| (t.due, Reverse(t.priority)));
+        tasks
+    }
+
+    /// raises incomplete tasks due by tomorrow to high priority, returning how
+    /// many changed
+    pub fn escalate_due_soon(&mut self, today: Date) -> usize {
+        let soon = today.add_days(1);
+        let mut escalated = 0;
+        for task in &mut self.tasks {
+            if !task.done && task.priority != Priority::High && task.due.is_some_and(|d| d <= soon)
+            {
+                task.priority = Priority::High;
+                escalated += 1;
+            }
         }
+        escalated
+    }
+
+    /// incomplete tasks that have a due date, soonest first
+    pub fn tasks_by_due(&self) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self
+            .todo()
+            .into_iter()
+            .filter(|t| t.due.is_some())
+            .collect();
+        tasks.sort_by_key(|t| t.due);
+        tasks
+    }
+
+    /// the existing ids numerically closest to `id`, in ascending order,
+    /// with one on each side when they are equally close
+    pub fn closest_ids(&self, id: u32) -> Vec<u32> {
+        let Some(distance) = self.tasks.iter().map(|t| t.id.abs_diff(id)).min() else {
+            return Vec::new();
+        };
+        let mut ids: Vec<u32> = self
+            .tasks
+            .iter()
+            .map(|t| t.id)
+            .filter(|t| t.abs_diff(id) == distance)
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    fn index_of(&self, id: u32) -> Result<usize, TodoError> {
+        self.tasks
+            .iter()
+            .position(|t| t.id == id)
+            .ok_or_else(|| self.not_found(id))
+    }
+
+    /// moves a task to a 1-based position, clamped to the end of the list
+    pub fn move_to(&mut self, id: u32, position: usize) -> Result<&Task, TodoError> {
+        let from = self.index_of(id)?;
+        let task = self.tasks.remove(from);
+        let to = position.saturating_sub(1).min(self.tasks.len());
+        self.tasks.insert(to, task);
+        Ok(&self.tasks[to])
+    }
+
+    pub fn move_before(&mut self, id: u32, anchor: u32) -> Result<&Task, TodoError> {
+        self.move_relative(id, anchor, 0)
+    }
+
+    pub fn move_after(&mut self, id: u32, anchor: u32) -> Result<&Task, TodoError> {
+        self.move_relative(id, anchor, 1)
+    }
+
+    fn move_relative(&mut self, id: u32, anchor: u32, offset: usize) -> Result<&Task, TodoError> {
+        let from = self.index_of(id)?;
+        self.index_of(anchor)?;
+        if id == anchor {
+            return Ok(&self.tasks[from]);
+        }
+        let task = self.tasks.remove(from);
+        let to = self.index_of(anchor)? + offset;
+        self.tasks.insert(to, task);
+        Ok(&self.tasks[to])
     }
 }
 
-#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, ValueEnum)]
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
 #[value(rename_all = "lower")]
+pub enum Status {
+    All,
+    Done,
+    Todo,
+}
+
+impl Status {
+    /// the `done` value to filter on, `None` for every task
+    pub fn done_filter(self) -> Option<bool> {
+        match self {
+            Status::All => None,
+            Status::Done => Some(true),
+            Status::Todo => Some(false),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "lower")]
+pub enum Sort {
+    /// the order tasks are kept in
+    #[default]
+    Position,
+    /// overdue first, then by priority, then by due date
+    Urgency,
+}
+
+impl std::fmt::Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sort::Position => write!(f, "position"),
+            Sort::Urgency => write!(f, "urgency"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Priority {
     #[default]
     Low,
@@ -97,6 +600,44 @@ pub enum Priority {
     High,
 }
 
+impl Priority {
+    pub fn all() -> [Priority; 3] {
+        [Priority::Low, Priority::Medium, Priority::High]
+    }
+
+    /// lowercase name, as accepted on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        }
+    }
+
+    /// 1 for low up to 3 for high
+    pub fn as_level(&self) -> u8 {
+        match self {
+            Priority::Low => 1,
+            Priority::Medium => 2,
+            Priority::High => 3,
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = TodoError;
+
+    /// a name like `high` or `med`, or a level from 1 to 3
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" | "1" => Ok(Priority::Low),
+            "medium" | "med" | "2" => Ok(Priority::Medium),
+            "high" | "3" => Ok(Priority::High),
+            _ => Err(TodoError::PriorityError),
+        }
+    }
+}
+
 impl std::fmt::Display for Priority {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -117,12 +658,18 @@ mod tests {
         let commands = [
             Command::Add {
                 text: String::from("eat mango"),
+                after: None,
+                priority: None,
             },
             Command::Add {
                 text: String::from("walk dog"),
+                after: None,
+                priority: None,
             },
             Command::Add {
                 text: String::from("pet ferris"),
+                after: None,
+                priority: None,
             },
         ];
 
@@ -164,4 +711,654 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_priority() -> Result<(), TodoError> {
+        for (s, priority) in [
+            ("low", Priority::Low),
+            ("Med", Priority::Medium),
+            ("medium", Priority::Medium),
+            ("HIGH", Priority::High),
+            ("1", Priority::Low),
+            ("2", Priority::Medium),
+            ("3", Priority::High),
+        ] {
+            assert_eq!(priority, s.parse()?);
+        }
+        for bad in ["0", "4", "-1", "urgent", ""] {
+            assert!(matches!(
+                bad.parse::<Priority>(),
+                Err(TodoError::PriorityError)
+            ));
+        }
+        let levels: Vec<u8> = Priority::all().iter().map(Priority::as_level).collect();
+        assert_eq!(vec![1, 2, 3], levels);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c", "d"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.set_priority(1, Priority::High)?;
+        task_list.set_priority(2, Priority::High)?;
+        task_list.mark_done(2)?;
+        task_list.mark_done(3)?;
+
+        let ids = |tasks: Vec<&Task>| tasks.iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(vec![1, 2, 3, 4], ids(task_list.filter(None, None)));
+        assert_eq!(vec![2, 3], ids(task_list.filter(Some(true), None)));
+        assert_eq!(
+            vec![1, 2],
+            ids(task_list.filter(None, Some(Priority::High)))
+        );
+        assert_eq!(
+            vec![1],
+            ids(task_list.filter(Some(false), Some(Priority::High)))
+        );
+        assert!(task_list.filter(None, Some(Priority::Medium)).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_matches() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["eat mango", "walk dog", "Buy mangoes"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.set_priority(1, Priority::High)?;
+        task_list.set_priority(3, Priority::High)?;
+        task_list.tag(3, &[String::from("Work")])?;
+        task_list.mark_done(1)?;
+
+        let ids = |clauses: &[Clause]| {
+            task_list
+                .query(clauses)
+                .iter()
+                .map(|t| t.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![1, 2, 3], ids(&[]));
+        assert_eq!(vec![1, 3], ids(&[Clause::Priority(Priority::High)]));
+        assert_eq!(
+            vec![3],
+            ids(&[Clause::Priority(Priority::High), Clause::Done(false)])
+        );
+        assert_eq!(vec![3], ids(&[Clause::Tag(String::from("work"))]));
+        assert_eq!(vec![1, 3], ids(&[Clause::Text(String::from("mango"))]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_completed_between() -> Result<(), TodoError> {
+        let start: Date = "2026-10-05".parse()?;
+        let end: Date = "2026-10-11".parse()?;
+        let mut task_list: TodoList = Default::default();
+        for text in ["before", "first day", "last day", "legacy", "todo"] {
+            task_list.add(String::from(text))?;
+        }
+        for (id, day) in [(1, start.add_days(-1)), (2, start), (3, end)] {
+            task_list.mark_done(id)?;
+            task_list.tasks[id as usize - 1].completed_at = Some(day.timestamp() as u64 + 600);
+        }
+        task_list.mark_done(4)?;
+        task_list.tasks[3].completed_at = None;
+
+        let ids: Vec<u32> = task_list
+            .completed_between(start, end)
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(vec![2, 3], ids);
+        Ok(())
+    }
+
+    #[test]
+    fn test_created_since() -> Result<(), TodoError> {
+        let now = 1_800_000_000;
+        let mut task_list: TodoList = Default::default();
+        for text in ["an hour ago", "last week", "a month ago", "legacy"] {
+            task_list.add(String::from(text))?;
+        }
+        let ages = [Some(3_600), Some(6 * 86_400), Some(30 * 86_400), None];
+        for (task, age) in task_list.tasks.iter_mut().zip(ages) {
+            task.created_at = age.map(|age| now - age);
+        }
+
+        let ids = |seconds| -> Vec<u32> {
+            task_list
+                .created_since(seconds, now)
+                .iter()
+                .map(|t| t.id)
+                .collect()
+        };
+        assert_eq!(vec![1], ids(86_400));
+        assert_eq!(vec![1, 2], ids(7 * 86_400));
+        Ok(())
+    }
+
+    #[test]
+    fn test_toggle_done() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("a"))?;
+
+        assert!(task_list.toggle_done(1)?.done);
+        assert!(task_list.get(1)?.completed_at.is_some());
+        let task = task_list.toggle_done(1)?;
+        assert!(!task.done);
+        assert_eq!(None, task.completed_at);
+        assert!(matches!(
+            task_list.toggle_done(2),
+            Err(TodoError::TaskNotFound { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_count() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.mark_done(1)?;
+
+        assert_eq!(3, task_list.count(Status::All));
+        assert_eq!(1, task_list.count(Status::Done));
+        assert_eq!(2, task_list.count(Status::Todo));
+        Ok(())
+    }
+
+    #[test]
+    fn test_append_text() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("buy milk"))?;
+
+        let task = task_list.append_text(1, " oat, not dairy ")?;
+        assert_eq!("buy milk oat, not dairy", task.text);
+        assert!(matches!(
+            task_list.append_text(1, "  "),
+            Err(TodoError::EmptyTask)
+        ));
+        assert!(matches!(
+            task_list.append_text(2, "more"),
+            Err(TodoError::TaskNotFound { .. })
+        ));
+        assert_eq!("buy milk oat, not dairy", task_list.get(1)?.text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_tags() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.tag(1, &[String::from("work"), String::from("Urgent")])?;
+        task_list.tag(2, &[String::from("Work"), String::from("home")])?;
+        task_list.tag(3, &[String::from("urgent"), String::from("URGENT")])?;
+
+        assert_eq!(vec!["home", "urgent", "work"], task_list.all_tags());
+        assert_eq!(vec!["urgent"], task_list.get(3)?.tags);
+        assert_eq!(2, task_list.tagged("WORK").len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_counts() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c", "d"] {
+            task_list.add(String::from(text))?;
+        }
+        assert!(task_list.tag_counts().is_empty());
+        task_list.tag(1, &[String::from("work"), String::from("home")])?;
+        task_list.tag(2, &[String::from("Work"), String::from("errand")])?;
+        task_list.tag(3, &[String::from("WORK"), String::from("Home")])?;
+
+        let expected = [("work", 3), ("home", 2), ("errand", 1)];
+        let expected: Vec<(String, usize)> =
+            expected.iter().map(|&(t, n)| (t.to_string(), n)).collect();
+        assert_eq!(expected, task_list.tag_counts());
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("water plants"))?;
+        task_list.set_priority(1, Priority::High)?;
+        task_list.mark_done(1)?;
+
+        let copy = task_list.duplicate(1)?;
+        assert_eq!(2, copy.id);
+        assert_eq!("water plants", copy.text);
+        assert_eq!(Priority::High, copy.priority);
+        assert!(!copy.done);
+        assert_eq!(3, task_list.next_id);
+        assert!(matches!(
+            task_list.duplicate(9),
+            Err(TodoError::TaskNotFound { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_priority_many() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+
+        let (updated, missing) = task_list.set_priority_many(&[3, 7, 1, 9], Priority::High);
+        assert_eq!(vec![3, 1], updated);
+        assert_eq!(vec![7, 9], missing);
+        assert_eq!(
+            vec![Priority::High, Priority::Low, Priority::High],
+            task_list
+                .tasks
+                .iter()
+                .map(|t| t.priority)
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["walk dog", "eat mango", "Walk Dog", "walk dog", "WALK DOG"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.move_to(1, 5)?;
+        task_list.mark_done(4)?;
+
+        assert_eq!(3, task_list.dedup());
+        assert_eq!(vec![2, 1], ids(&task_list));
+        assert!(task_list.get(1)?.done);
+        assert!(!task_list.get(2)?.done);
+        assert_eq!(6, task_list.next_id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_tag() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.tag(1, &[String::from("wrk"), String::from("home")])?;
+        task_list.tag(2, &[String::from("work"), String::from("WRK")])?;
+        task_list.tag(3, &[String::from("home")])?;
+
+        assert_eq!(2, task_list.rename_tag("wrk", "work")?);
+        assert_eq!(vec!["work", "home"], task_list.get(1)?.tags);
+        assert_eq!(vec!["work"], task_list.get(2)?.tags);
+        assert_eq!(vec!["home"], task_list.get(3)?.tags);
+        assert_eq!(0, task_list.rename_tag("wrk", "work")?);
+        assert_eq!(2, task_list.rename_tag("home", " house ")?);
+        assert_eq!(vec!["work", "house"], task_list.get(1)?.tags);
+        assert!(matches!(
+            task_list.rename_tag("work", "  "),
+            Err(TodoError::MissingArgument)
+        ));
+        assert_eq!(vec!["work"], task_list.get(2)?.tags);
+        Ok(())
+    }
+
+    #[test]
+    fn test_id_at_index() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.move_to(3, 1)?;
+
+        assert_eq!(3, task_list.id_at_index(1)?);
+        assert_eq!(2, task_list.id_at_index(3)?);
+        assert!(matches!(
+            task_list.id_at_index(0),
+            Err(TodoError::IndexOutOfRange(0))
+        ));
+        assert!(matches!(
+            task_list.id_at_index(4),
+            Err(TodoError::IndexOutOfRange(4))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("a"))?;
+        task_list.add(String::from("b"))?;
+        let mut other: TodoList = Default::default();
+        other.add(String::from("c"))?;
+        other.add(String::from("d"))?;
+        other.set_priority(2, Priority::High)?;
+        other.mark_done(2)?;
+        let mut expected = other.tasks[1].clone();
+        expected.id = 4;
+
+        assert_eq!(2, task_list.merge(other));
+        assert_eq!(vec![1, 2, 3, 4], ids(&task_list));
+        assert_eq!(&expected, task_list.get(4)?);
+        assert_eq!(5, task_list.next_id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("a"))?;
+        task_list.add(String::from("b"))?;
+        task_list.reset();
+
+        assert!(task_list.tasks.is_empty());
+        assert_eq!(1, task_list.add(String::from("c"))?.id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_completion_rate() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        assert_eq!(0.0, task_list.completion_rate());
+
+        for text in ["a", "b", "c", "d"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.mark_done(2)?;
+        assert_eq!(0.25, task_list.completion_rate());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sorted_by_urgency() -> Result<(), TodoError> {
+        let today: Date = "2026-10-14".parse()?;
+        let mut task_list: TodoList = Default::default();
+        for text in [
+            "no due",
+            "high soon",
+            "low late",
+            "medium late",
+            "low soon",
+            "done late",
+        ] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.set_priority(2, Priority::High)?;
+        task_list.set_due(2, today.add_days(3))?;
+        task_list.set_due(3, today.add_days(-1))?;
+        task_list.set_priority(4, Priority::Medium)?;
+        task_list.set_due(4, today.add_days(-5))?;
+        task_list.set_due(5, today.add_days(1))?;
+        task_list.set_due(6, today.add_days(-9))?;
+        task_list.mark_done(6)?;
+
+        let order: Vec<u32> = task_list
+            .sorted_by_urgency(today)
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(vec![4, 3, 2, 5, 1], order);
+        Ok(())
+    }
+
+    #[test]
+    fn test_due_today_or_overdue() -> Result<(), TodoError> {
+        let today: Date = "2026-10-14".parse()?;
+        let mut task_list: TodoList = Default::default();
+        for text in [
+            "today low",
+            "yesterday",
+            "today high",
+            "tomorrow",
+            "no due",
+            "done",
+        ] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.set_due(1, today)?;
+        task_list.set_due(2, today.add_days(-1))?;
+        task_list.set_due(3, today)?;
+        task_list.set_priority(3, Priority::High)?;
+        task_list.set_due(4, today.add_days(1))?;
+        task_list.set_due(6, today)?;
+        task_list.mark_done(6)?;
+
+        let order: Vec<u32> = task_list
+            .due_today_or_overdue(today)
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(vec![2, 3, 1], order);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tasks_by_due() -> Result<(), TodoError> {
+        let today: Date = "2026-10-14".parse()?;
+        let mut task_list: TodoList = Default::default();
+        for text in ["next week", "no due", "yesterday", "done", "tomorrow"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.set_due(1, today.add_days(7))?;
+        task_list.set_due(3, today.add_days(-1))?;
+        task_list.set_due(4, today)?;
+        task_list.mark_done(4)?;
+        task_list.set_due(5, today.add_days(1))?;
+
+        let order: Vec<u32> = task_list.tasks_by_due().iter().map(|t| t.id).collect();
+        assert_eq!(vec![3, 5, 1], order);
+        Ok(())
+    }
+
+    #[test]
+    fn test_escalate_due_soon() -> Result<(), TodoError> {
+        let today: Date = "2026-10-14".parse()?;
+        let mut task_list: TodoList = Default::default();
+        for text in ["tomorrow", "next week", "no due"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.set_due(1, today.add_days(1))?;
+        task_list.set_due(2, today.add_days(7))?;
+
+        assert_eq!(1, task_list.escalate_due_soon(today));
+        assert_eq!(Priority::High, task_list.get(1)?.priority);
+        assert_eq!(Priority::Low, task_list.get(2)?.priority);
+        assert_eq!(Priority::Low, task_list.get(3)?.priority);
+        assert_eq!(0, task_list.escalate_due_soon(today));
+        Ok(())
+    }
+
+    #[test]
+    fn test_closest_ids() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        assert!(task_list.closest_ids(3).is_empty());
+
+        for text in ["a", "b", "c", "d", "e"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.tasks.retain(|t| t.id != 3);
+
+        assert_eq!(vec![2, 4], task_list.closest_ids(3));
+        assert_eq!(vec![5], task_list.closest_ids(15));
+        assert_eq!(vec![1], task_list.closest_ids(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_id() {
+        let task = Task {
+            id: 0,
+            text: String::from("fine"),
+            ..Default::default()
+        };
+        assert_eq!(vec!["id must be greater than 0"], task.validate());
+    }
+
+    #[test]
+    fn test_validate_text() {
+        let task = Task {
+            id: 1,
+            text: String::from("  \t "),
+            ..Default::default()
+        };
+        assert_eq!(vec!["text is empty"], task.validate());
+    }
+
+    #[test]
+    fn test_validate_due_before_created() -> Result<(), TodoError> {
+        let created: Date = "2026-10-14".parse()?;
+        let mut task = Task {
+            id: 1,
+            text: String::from("fine"),
+            due: Some(created.add_days(-1)),
+            created_at: Some(1_791_979_200),
+            ..Default::default()
+        };
+        assert_eq!(created, Date::from_timestamp(1_791_979_200));
+        assert_eq!(
+            vec!["due date is before the task was created"],
+            task.validate()
+        );
+
+        task.due = Some(created);
+        assert!(task.validate().is_empty());
+        task.created_at = None;
+        task.due = Some(created.add_days(-1));
+        assert!(task.validate().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_list() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("fine"))?;
+        assert!(task_list.validate().is_empty());
+
+        task_list.tasks.push(Task {
+            id: 2,
+            ..Default::default()
+        });
+        assert_eq!(vec!["task 2: text is empty"], task_list.validate());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("a"))?;
+        task_list.add(String::from("b"))?;
+        assert!(task_list.verify().is_empty());
+
+        task_list.tasks[1].id = 1;
+        task_list.tasks.push(Task {
+            id: 7,
+            text: String::from(" "),
+            ..Default::default()
+        });
+        assert_eq!(
+            vec![
+                "task 1: id is used more than once",
+                "task 7: id is not below next_id 3",
+                "task 7: text is empty",
+            ],
+            task_list.verify()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_rejects_empty_text() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        assert!(matches!(
+            task_list.add(String::from("")),
+            Err(TodoError::EmptyTask)
+        ));
+        assert!(matches!(
+            task_list.add(String::from(" \n\t ")),
+            Err(TodoError::EmptyTask)
+        ));
+        assert!(task_list.tasks.is_empty());
+        assert_eq!(1, task_list.next_id);
+
+        let task = task_list.add(String::from(" buy milk "))?;
+        assert_eq!("buy milk", task.text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_with_priority() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        assert_eq!(Priority::Low, task_list.add(String::from("a"))?.priority);
+        let task = task_list.add_with_priority(String::from("b"), Priority::High)?;
+        assert_eq!(Priority::High, task.priority);
+        assert_eq!(2, task.id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_after() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+
+        let task = task_list.add_after(String::from("d"), 1)?;
+        assert_eq!(4, task.id);
+        assert_eq!(vec![1, 4, 2, 3], ids(&task_list));
+
+        assert!(matches!(
+            task_list.add_after(String::from("e"), 42),
+            Err(TodoError::TaskNotFound { .. })
+        ));
+        assert_eq!(5, task_list.next_id);
+        Ok(())
+    }
+
+    fn ids(task_list: &TodoList) -> Vec<u32> {
+        task_list.tasks.iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn test_move_before() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c", "d", "e"] {
+            task_list.add(String::from(text))?;
+        }
+
+        task_list.move_before(5, 2)?;
+        assert_eq!(vec![1, 5, 2, 3, 4], ids(&task_list));
+
+        task_list.move_before(1, 4)?;
+        assert_eq!(vec![5, 2, 3, 1, 4], ids(&task_list));
+
+        assert!(matches!(
+            task_list.move_before(3, 42),
+            Err(TodoError::TaskNotFound { .. })
+        ));
+        assert_eq!(vec![5, 2, 3, 1, 4], ids(&task_list));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_after() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c", "d", "e"] {
+            task_list.add(String::from(text))?;
+        }
+
+        task_list.move_after(1, 4)?;
+        assert_eq!(vec![2, 3, 4, 1, 5], ids(&task_list));
+
+        task_list.move_after(2, 5)?;
+        assert_eq!(vec![3, 4, 1, 5, 2], ids(&task_list));
+
+        assert!(matches!(
+            task_list.move_after(3, 42),
+            Err(TodoError::TaskNotFound { .. })
+        ));
+        Ok(())
+    }
 }
@@ -1,46 +1,418 @@
 use crate::cli::TodoError;
-use crate::model::TodoList;
+use crate::model::{SCHEMA_VERSION, Task, TodoList};
 use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
 
 pub const PATH: &str = "src/todo.json";
+/// how many previous versions of the list are kept around on save
+pub const DEFAULT_BACKUPS: usize = 3;
 
-pub fn load_todo_list(path: &str) -> TodoList {
+pub fn load_todo_list(path: &str) -> Result<TodoList, TodoError> {
     match fs::read_to_string(path) {
-        Ok(contents) => serde_json::from_str::<TodoList>(&contents).unwrap_or_default(),
-        Err(_) => TodoList::default(),
+        Ok(contents) => migrate(serde_json::from_str::<TodoList>(&contents)?),
+        // a missing file is a fresh list, anything else must not be overwritten
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(TodoList::default()),
+        Err(source) => Err(TodoError::LoadError {
+            path: path.to_string(),
+            source,
+        }),
     }
 }
 
+/// upgrades a list read from an older file to the current schema, refusing
+/// files from a newer build whose unknown fields would be lost on save
+fn migrate(mut list: TodoList) -> Result<TodoList, TodoError> {
+    if list.version > SCHEMA_VERSION {
+        return Err(TodoError::UnsupportedVersion(list.version));
+    }
+    // v0 had no version field; fields added since then all have defaults
+    list.version = SCHEMA_VERSION;
+    Ok(list)
+}
+
+/// whether `name` can be pasted into a file name next to the list: a dot
+/// would let a profile and a snapshot map onto the same file, a separator
+/// would leave the directory
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', '/', '\\'])
+}
+
+/// path of the file backing a named profile, kept next to `base`
+pub fn profile_path(base: &str, profile: &str) -> Result<String, TodoError> {
+    if !is_plain_name(profile) {
+        return Err(TodoError::InvalidProfileName);
+    }
+    let stem = base.strip_suffix(".json").unwrap_or(base);
+    Ok(format!("{stem}.{profile}.json"))
+}
+
+/// path of the named snapshot of `base`
+pub fn snapshot_path(base: &str, name: &str) -> String {
+    let stem = base.strip_suffix(".json").unwrap_or(base);
+    format!("{stem}.{name}.snapshot.json")
+}
+
+pub fn save_snapshot(base: &str, name: &str, list: &TodoList) -> Result<(), TodoError> {
+    if !is_plain_name(name) {
+        return Err(TodoError::InvalidSnapshotName);
+    }
+    save_todo_list(&snapshot_path(base, name), list)
+}
+
+pub fn load_snapshot(base: &str, name: &str) -> Result<TodoList, TodoError> {
+    let path = snapshot_path(base, name);
+    if !Path::new(&path).exists() {
+        return Err(TodoError::SnapshotNotFound);
+    }
+    load_todo_list(&path)
+}
+
+/// names of the snapshots taken of `base`, sorted; names with a dot come
+/// from a profile of `base` and are left out
+pub fn list_snapshots(base: &str) -> Vec<String> {
+    let base = Path::new(base);
+    let dir = match base.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let prefix = match base.file_stem().and_then(|s| s.to_str()) {
+        Some(stem) => format!("{stem}."),
+        None => return Vec::new(),
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|file| {
+            let name = file.strip_prefix(&prefix)?.strip_suffix(".snapshot.json")?;
+            (!name.contains('.')).then(|| name.to_string())
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// path of the file holding tasks archived out of `base`
+pub fn archive_path(base: &str) -> String {
+    let stem = base.strip_suffix(".json").unwrap_or(base);
+    format!("{stem}_archive.json")
+}
+
+pub fn load_archive(path: &str) -> Result<Vec<Task>, TodoError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(source) => Err(TodoError::LoadError {
+            path: path.to_string(),
+            source,
+        }),
+    }
+}
+
+/// adds `tasks` to the end of the archive, keeping what is already there
+pub fn append_archive(path: &str, tasks: &[Task]) -> Result<(), TodoError> {
+    let mut archive = load_archive(path)?;
+    archive.extend_from_slice(tasks);
+    let json =
+        serde_json::to_string_pretty(&archive).map_err(|e| TodoError::SaveError(e.into()))?;
+    write_atomic(path, &json)?;
+    Ok(())
+}
+
+/// makes sure the directory `path` will be written into exists
+fn create_parent_dir(path: &str) -> std::io::Result<()> {
+    match Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+/// writes `contents` to a temporary file next to `path` and renames it into
+/// place, so an interrupted write never leaves `path` half written
+pub fn write_atomic(path: &str, contents: &str) -> std::io::Result<()> {
+    create_parent_dir(path)?;
+    let tmp = format!("{path}.tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// how the saved JSON is laid out; either loads back the same
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum JsonStyle {
+    #[default]
+    Pretty,
+    Compact,
+}
+
 pub fn save_todo_list(path: &str, list: &TodoList) -> Result<(), TodoError> {
-    let json = serde_json::to_string_pretty(list).map_err(|e| TodoError::SaveError(e.into()))?;
-    std::fs::write(path, json).map_err(TodoError::SaveError)?;
+    save_todo_list_as(path, list, JsonStyle::Pretty)
+}
+
+pub fn save_todo_list_as(path: &str, list: &TodoList, style: JsonStyle) -> Result<(), TodoError> {
+    let json = match style {
+        JsonStyle::Pretty => serde_json::to_string_pretty(list),
+        JsonStyle::Compact => serde_json::to_string(list),
+    }
+    .map_err(|e| TodoError::SaveError(e.into()))?;
+    write_atomic(path, &json)?;
+    Ok(())
+}
+
+/// path of the `n`th most recent backup of `path`, counting from 1
+pub fn backup_path(path: &str, n: usize) -> String {
+    format!("{path}.{n}")
+}
+
+/// shifts `path.1` to `path.2` and so on, dropping anything past `keep`,
+/// then copies the current file to `path.1`
+pub fn rotate_backups(path: &str, keep: usize) -> Result<(), TodoError> {
+    if keep == 0 || !Path::new(path).is_file() {
+        return Ok(());
+    }
+    for n in (1..keep).rev() {
+        let from = backup_path(path, n);
+        if Path::new(&from).exists() {
+            fs::rename(&from, backup_path(path, n + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
     Ok(())
 }
 
+/// saves the list, keeping up to `keep` previous versions as backups
+pub fn save_with_backups(
+    path: &str,
+    list: &TodoList,
+    style: JsonStyle,
+    keep: usize,
+) -> Result<(), TodoError> {
+    rotate_backups(path, keep)?;
+    save_todo_list_as(path, list, style)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::cli::{Command, execute_command};
 
     #[test]
-    fn test_load_todo() {
-        let list = load_todo_list("tests/data/test.json");
+    fn test_load_todo() -> Result<(), TodoError> {
+        let list = load_todo_list("tests/data/test.json")?;
         assert_eq!(3, list.tasks.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_error_keeps_cause() {
+        let path = "tests/data/corrupt_test.json";
+        let _ = std::fs::write(path, "{ \"tasks\": [");
+        let result = load_todo_list(path);
+        let _ = std::fs::remove_file(path);
+
+        match result {
+            Err(e @ TodoError::ParseError(_)) => {
+                use std::error::Error;
+                assert!(e.to_string().contains("EOF"));
+                assert!(e.source().is_some());
+            }
+            _ => panic!("expected a load error"),
+        }
+    }
+
+    #[test]
+    fn test_load_unreadable_path() {
+        assert!(load_todo_list("tests/data/missing_test.json").is_ok());
+        assert!(matches!(
+            load_todo_list("tests/data"),
+            Err(TodoError::LoadError { .. })
+        ));
+        assert!(load_archive("tests/data").is_err());
     }
 
     #[test]
     fn test_save_todo() -> Result<(), TodoError> {
         let cmd = Command::Add {
             text: String::from("hello there"),
+            after: None,
+            priority: None,
         };
         let mut task_list: TodoList = Default::default();
         let path = "tests/data/save_test.json";
         execute_command(cmd, &mut task_list)?;
         let _ = save_todo_list(path, &task_list);
-        let saved = load_todo_list(path);
-        assert_eq!(1, saved.tasks.len());
+        let saved = load_todo_list(path)?;
+        assert_eq!(task_list, saved);
         // cleanup
         let _ = std::fs::remove_file(path);
         Ok(())
     }
+
+    #[test]
+    fn test_snapshot_round_trip() -> Result<(), TodoError> {
+        let base = "tests/data/snapshot_test.json";
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("before reorg"))?;
+        save_snapshot(base, "before", &task_list)?;
+
+        task_list.add(String::from("after reorg"))?;
+        save_snapshot(base, "after", &task_list)?;
+
+        let names = list_snapshots(base);
+        let restored = load_snapshot(base, "before");
+        let _ = std::fs::remove_file(snapshot_path(base, "before"));
+        let _ = std::fs::remove_file(snapshot_path(base, "after"));
+
+        assert_eq!(vec!["after", "before"], names);
+        let restored = restored?;
+        assert_eq!(1, restored.tasks.len());
+        assert_eq!("before reorg", restored.tasks[0].text);
+        assert!(matches!(
+            load_snapshot(base, "missing"),
+            Err(TodoError::SnapshotNotFound)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_v0() -> Result<(), TodoError> {
+        let list = load_todo_list("tests/data/v0.json")?;
+        assert_eq!(SCHEMA_VERSION, list.version);
+        assert_eq!("Water plants", list.tasks[0].text);
+        assert!(list.tasks[0].done);
+
+        let path = "tests/data/migrate_test.json";
+        save_todo_list(path, &list)?;
+        let raw = std::fs::read_to_string(path);
+        let _ = std::fs::remove_file(path);
+        let raw: serde_json::Value = serde_json::from_str(&raw.unwrap()).unwrap();
+        assert_eq!(SCHEMA_VERSION, raw["version"].as_u64().unwrap() as u32);
+        Ok(())
+    }
+
+    #[test]
+    fn test_future_version() {
+        let path = "tests/data/future_test.json";
+        let json = format!(
+            r#"{{"version": {}, "tasks": [], "next_id": 1}}"#,
+            SCHEMA_VERSION + 1
+        );
+        let _ = std::fs::write(path, json);
+        let result = load_todo_list(path);
+        let _ = std::fs::remove_file(path);
+
+        assert!(matches!(
+            result,
+            Err(TodoError::UnsupportedVersion(v)) if v == SCHEMA_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_save_creates_parent_dirs() -> Result<(), TodoError> {
+        let dir = "tests/data/nested_test";
+        let path = "tests/data/nested_test/a/b/todo.json";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("hello there"))?;
+
+        let saved = save_todo_list(path, &task_list);
+        let exists = Path::new(path).is_file();
+        let _ = std::fs::remove_dir_all(dir);
+        saved?;
+        assert!(exists);
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_with_backups() -> Result<(), TodoError> {
+        let path = "tests/data/backup_test.json";
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c", "d"] {
+            task_list.add(String::from(text))?;
+            save_with_backups(path, &task_list, JsonStyle::Pretty, 2)?;
+        }
+        let lens: Vec<Option<usize>> = (0..=3)
+            .map(|n| {
+                let file = if n == 0 {
+                    path.to_string()
+                } else {
+                    backup_path(path, n)
+                };
+                load_todo_list(&file).ok().map(|l| l.tasks.len())
+            })
+            .collect();
+        let third = Path::new(&backup_path(path, 3)).exists();
+        for n in 0..=3 {
+            let _ = std::fs::remove_file(backup_path(path, n));
+        }
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(vec![Some(4), Some(3), Some(2)], lens[..3]);
+        assert!(!third);
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_styles() -> Result<(), TodoError> {
+        let mut task_list: TodoList = Default::default();
+        task_list.add(String::from("hello there"))?;
+        task_list.add(String::from("goodbye there"))?;
+
+        for (style, path) in [
+            (JsonStyle::Pretty, "tests/data/pretty_test.json"),
+            (JsonStyle::Compact, "tests/data/compact_test.json"),
+        ] {
+            save_todo_list_as(path, &task_list, style)?;
+            let raw = std::fs::read_to_string(path);
+            let loaded = load_todo_list(path);
+            let _ = std::fs::remove_file(path);
+
+            let lines = raw.unwrap().lines().count();
+            assert_eq!(style == JsonStyle::Compact, lines == 1);
+            assert_eq!(2, loaded?.tasks.len());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive() -> Result<(), TodoError> {
+        let path = "tests/data/archive_test.json";
+        let mut task_list: TodoList = Default::default();
+        for text in ["a", "b", "c"] {
+            task_list.add(String::from(text))?;
+        }
+        task_list.mark_done(1)?;
+        task_list.mark_done(3)?;
+
+        let done = task_list.take_done();
+        append_archive(path, &done)?;
+        task_list.mark_done(2)?;
+        append_archive(path, &task_list.take_done())?;
+        let archive = load_archive(path);
+        let _ = std::fs::remove_file(path);
+
+        let ids: Vec<u32> = archive?.iter().map(|t| t.id).collect();
+        assert_eq!(vec![1, 3, 2], ids);
+        assert!(task_list.tasks.is_empty());
+        assert_eq!(4, task_list.next_id);
+        assert!(load_archive(path)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_path() -> Result<(), TodoError> {
+        assert_eq!("src/todo.work.json", profile_path(PATH, "work")?);
+        assert_eq!("lists/a.home.json", profile_path("lists/a.json", "home")?);
+        assert_eq!("src/todo_archive.json", archive_path(PATH));
+        for bad in ["", "../../tmp/evil", "daily.snapshot", "a\\b"] {
+            assert!(matches!(
+                profile_path(PATH, bad),
+                Err(TodoError::InvalidProfileName)
+            ));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,77 @@
+use crate::cli::TodoError;
+use crate::model::Priority;
+
+/// one `key:value` condition of a query, see `Task::matches`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Clause {
+    Priority(Priority),
+    Done(bool),
+    Tag(String),
+    /// case-insensitive substring of the task text
+    Text(String),
+}
+
+/// parses a space separated query like `priority:high done:false tag:work`,
+/// where every clause has to hold for a task to match
+pub fn parse_query(expr: &str) -> Result<Vec<Clause>, TodoError> {
+    expr.split_whitespace().map(parse_clause).collect()
+}
+
+fn parse_clause(term: &str) -> Result<Clause, TodoError> {
+    let Some((key, value)) = term.split_once(':') else {
+        return Err(TodoError::QueryError(format!(
+            "expected key:value, got `{term}`"
+        )));
+    };
+    let invalid = || TodoError::QueryError(format!("invalid value for {key}: `{value}`"));
+    match key {
+        "priority" => value.parse().map(Clause::Priority).map_err(|_| invalid()),
+        "done" => match value {
+            "true" | "yes" => Ok(Clause::Done(true)),
+            "false" | "no" => Ok(Clause::Done(false)),
+            _ => Err(invalid()),
+        },
+        "tag" if !value.is_empty() => Ok(Clause::Tag(value.to_string())),
+        "text" if !value.is_empty() => Ok(Clause::Text(value.to_lowercase())),
+        "tag" | "text" => Err(invalid()),
+        _ => Err(TodoError::QueryError(format!(
+            "unknown key `{key}`, expected priority, done, tag or text"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() -> Result<(), TodoError> {
+        assert_eq!(
+            vec![
+                Clause::Priority(Priority::High),
+                Clause::Done(false),
+                Clause::Tag(String::from("work")),
+                Clause::Text(String::from("mango")),
+            ],
+            parse_query("priority:high  done:false tag:work text:Mango")?
+        );
+        assert!(parse_query("")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_query_errors() {
+        for (expr, message) in [
+            ("colour:red", "unknown key `colour`"),
+            ("priority:urgent", "invalid value for priority"),
+            ("done:maybe", "invalid value for done"),
+            ("tag:", "invalid value for tag"),
+            ("high", "expected key:value"),
+        ] {
+            match parse_query(expr) {
+                Err(TodoError::QueryError(e)) => assert!(e.starts_with(message), "{e}"),
+                _ => panic!("expected `{expr}` to be rejected"),
+            }
+        }
+    }
+}